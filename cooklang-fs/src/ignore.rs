@@ -0,0 +1,202 @@
+//! `.chefignore` files
+//!
+//! They use the gitignore syntax: one glob per line, `#` comments, `!` to
+//! negate, a trailing `/` to only match dirs and a `/` at the start or middle
+//! to match relative to the file location. Patterns without a `/` match at any
+//! level.
+//!
+//! An ignore file applies to the dir it is in and all its subdirs. Files in
+//! subdirs take precedence over the ones in their parents and, in the same
+//! file, the last matching pattern wins. Like in git, a file can't be
+//! re-included if one of its parent dirs is ignored.
+
+use std::sync::Arc;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Name of the ignore files
+pub const IGNORE_FILE: &str = ".chefignore";
+
+#[derive(Debug)]
+struct Rule {
+    glob: GlobMatcher,
+    negate: bool,
+    dir_only: bool,
+}
+
+#[derive(Debug)]
+struct IgnoreFile {
+    dir: Utf8PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    fn parse(dir: &Utf8Path, text: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in text.lines() {
+            let pattern = line.trim_end();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+            let (negate, pattern) = match pattern.strip_prefix('!') {
+                Some(p) => (true, p),
+                None => (
+                    false,
+                    pattern
+                        .strip_prefix('\\')
+                        .filter(|p| p.starts_with(['!', '#']))
+                        .unwrap_or(pattern),
+                ),
+            };
+            let dir_only = pattern.ends_with('/');
+            let pattern = pattern.trim_end_matches('/');
+            if pattern.is_empty() {
+                continue;
+            }
+            let pattern = if pattern.contains('/') {
+                pattern.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{pattern}")
+            };
+
+            match GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()
+            {
+                Ok(glob) => rules.push(Rule {
+                    glob: glob.compile_matcher(),
+                    negate,
+                    dir_only,
+                }),
+                Err(err) => {
+                    tracing::warn!("Invalid pattern in '{dir}/{IGNORE_FILE}': {err}");
+                }
+            }
+        }
+        Self {
+            dir: dir.to_path_buf(),
+            rules,
+        }
+    }
+
+    fn load(dir: &Utf8Path) -> Option<Self> {
+        let path = dir.join(IGNORE_FILE);
+        if !path.is_file() {
+            return None;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(Self::parse(dir, &text)),
+            Err(err) => {
+                tracing::warn!("Could not read '{path}': {err}");
+                None
+            }
+        }
+    }
+
+    /// `Some(true)` if ignored, `Some(false)` if re-included and `None` if no
+    /// pattern matches
+    fn matched(&self, path: &Utf8Path, is_dir: bool) -> Option<bool> {
+        let rel = path.strip_prefix(&self.dir).ok()?;
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| (is_dir || !r.dir_only) && r.glob.is_match(rel.as_std_path()))
+            .map(|r| !r.negate)
+    }
+}
+
+/// Ignore files that apply to a dir, outermost first
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnoreStack(Vec<Arc<IgnoreFile>>);
+
+impl IgnoreStack {
+    /// Ignore files from `base_path` down to `dir`, both included
+    ///
+    /// `None` if `dir` itself is ignored.
+    pub(crate) fn for_dir(base_path: &Utf8Path, dir: &Utf8Path) -> Option<Self> {
+        let mut stack = Self::default().push_dir(base_path);
+        let Ok(rel) = dir.strip_prefix(base_path) else {
+            return Some(Self::default().push_dir(dir));
+        };
+        let mut current = base_path.to_path_buf();
+        for component in rel.components() {
+            current.push(component);
+            if component.as_str().starts_with('.') || stack.is_ignored(&current, true) {
+                return None;
+            }
+            stack = stack.push_dir(&current);
+        }
+        Some(stack)
+    }
+
+    /// Adds the ignore file of `dir`, if any
+    pub(crate) fn push_dir(&self, dir: &Utf8Path) -> Self {
+        let mut stack = self.clone();
+        if let Some(file) = IgnoreFile::load(dir) {
+            stack.0.push(Arc::new(file));
+        }
+        stack
+    }
+
+    /// Checks a path in the dir of the stack
+    ///
+    /// This does not check the parent dirs.
+    pub(crate) fn is_ignored(&self, path: &Utf8Path, is_dir: bool) -> bool {
+        self.0
+            .iter()
+            .rev()
+            .find_map(|f| f.matched(path, is_dir))
+            .unwrap_or(false)
+    }
+}
+
+/// Checks if a path is ignored by the `.chefignore` files of a collection
+///
+/// Files and dirs starting with `.` are also ignored. `path` can be relative
+/// to `base_path` or prefixed with it. Paths outside `base_path` are never
+/// ignored.
+pub fn is_ignored(base_path: &Utf8Path, path: &Utf8Path) -> bool {
+    let path = if path.starts_with(base_path) {
+        path.to_path_buf()
+    } else if path.is_relative() {
+        base_path.join(path)
+    } else {
+        return false;
+    };
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return false;
+    };
+    if path == base_path {
+        return false;
+    }
+    match IgnoreStack::for_dir(base_path, parent) {
+        Some(stack) => name.starts_with('.') || stack.is_ignored(&path, path.is_dir()),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitignore_patterns() {
+        let file = IgnoreFile::parse(
+            Utf8Path::new("base"),
+            "# comment\ndrafts/\n*.old.cook\n!keep.old.cook\n/archive\nsub/*.cook\n",
+        );
+        let m = |p: &str, dir| file.matched(Utf8Path::new(p), dir);
+        assert_eq!(m("base/drafts", true), Some(true));
+        assert_eq!(m("base/a/drafts", true), Some(true));
+        assert_eq!(m("base/drafts", false), None);
+        assert_eq!(m("base/x/pasta.old.cook", false), Some(true));
+        assert_eq!(m("base/keep.old.cook", false), Some(false));
+        assert_eq!(m("base/archive", true), Some(true));
+        assert_eq!(m("base/a/archive", true), None);
+        assert_eq!(m("base/sub/a.cook", false), Some(true));
+        assert_eq!(m("base/sub/deep/a.cook", false), None);
+        assert_eq!(m("other/drafts", true), None);
+    }
+}
@@ -7,6 +7,8 @@
 //! from a path. The index can be lazy or eager. Both created with
 //! [`new_index`].
 
+mod ignore;
+mod symlinks;
 mod walker;
 
 use std::{cell::RefCell, collections::HashMap};
@@ -16,6 +18,9 @@ use cooklang::quantity::QuantityValue;
 use once_cell::sync::OnceCell;
 use serde::Serialize;
 
+use ignore::IgnoreStack;
+pub use ignore::{is_ignored, IGNORE_FILE};
+pub use symlinks::Symlinks;
 pub use walker::DirEntry;
 use walker::Walker;
 
@@ -29,6 +34,7 @@ pub fn new_index(
 pub struct FsIndexBuilder {
     base_path: Utf8PathBuf,
     walker: Walker,
+    symlinks: Symlinks,
 }
 
 impl FsIndexBuilder {
@@ -42,6 +48,7 @@ impl FsIndexBuilder {
         Ok(Self {
             base_path: base_path.to_path_buf(),
             walker,
+            symlinks: Symlinks::default(),
         })
     }
 
@@ -61,6 +68,13 @@ impl FsIndexBuilder {
         self
     }
 
+    /// Sets how to handle symbolic links
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.walker.set_symlinks(symlinks.clone());
+        self.symlinks = symlinks;
+        self
+    }
+
     /// Create a new [lazy index](`LazyFsIndex`)
     ///
     /// The structure this creates is not completely thread safe, see
@@ -68,6 +82,7 @@ impl FsIndexBuilder {
     pub fn lazy(self) -> LazyFsIndex {
         LazyFsIndex {
             base_path: self.base_path,
+            symlinks: self.symlinks,
             walker: RefCell::new(self.walker),
             cache: RefCell::new(Cache::default()),
         }
@@ -79,6 +94,7 @@ impl FsIndexBuilder {
         index_all(&mut cache, &mut self.walker)?;
         Ok(FsIndex {
             base_path: self.base_path,
+            symlinks: self.symlinks,
             cache,
         })
     }
@@ -109,6 +125,7 @@ fn index_all(cache: &mut Cache, walker: &mut Walker) -> Result<(), Error> {
 #[derive(Debug)]
 pub struct LazyFsIndex {
     base_path: Utf8PathBuf,
+    symlinks: Symlinks,
     cache: RefCell<Cache>,
     walker: RefCell<Walker>,
 }
@@ -119,6 +136,7 @@ pub struct LazyFsIndex {
 #[derive(Debug)]
 pub struct FsIndex {
     base_path: Utf8PathBuf,
+    symlinks: Symlinks,
     cache: Cache,
 }
 
@@ -166,7 +184,7 @@ impl FsIndex {
         recipe: &str,
         relative_to: Option<&Utf8Path>,
     ) -> Result<RecipeEntry, Error> {
-        try_path(recipe, relative_to, &self.base_path).or_else(|_| self.get(recipe))
+        try_path(recipe, relative_to, &self.base_path, &self.symlinks).or_else(|_| self.get(recipe))
     }
 
     pub fn get(&self, recipe: &str) -> Result<RecipeEntry, Error> {
@@ -254,6 +272,7 @@ impl LazyFsIndex {
         index_all(&mut cache, &mut walker)?;
         Ok(FsIndex {
             base_path: self.base_path,
+            symlinks: self.symlinks,
             cache,
         })
     }
@@ -267,7 +286,7 @@ impl LazyFsIndex {
         recipe: &str,
         relative_to: Option<&Utf8Path>,
     ) -> Result<RecipeEntry, Error> {
-        try_path(recipe, relative_to, &self.base_path).or_else(|_| self.get(recipe))
+        try_path(recipe, relative_to, &self.base_path, &self.symlinks).or_else(|_| self.get(recipe))
     }
 
     /// Get a recipe from the index
@@ -364,15 +383,28 @@ fn compare_path(full: &Utf8Path, suffix: &Utf8Path) -> bool {
 }
 
 /// Get all recipes from a path with a depth limit
+///
+/// Links are not followed, see [`all_recipes_with`].
 pub fn all_recipes(
     base_path: impl AsRef<std::path::Path>,
     max_depth: usize,
+) -> Result<impl Iterator<Item = RecipeEntry>, std::io::Error> {
+    all_recipes_with(base_path, max_depth, &Symlinks::default())
+}
+
+/// Like [`all_recipes`], handling links as `symlinks` says
+pub fn all_recipes_with(
+    base_path: impl AsRef<std::path::Path>,
+    max_depth: usize,
+    symlinks: &Symlinks,
 ) -> Result<impl Iterator<Item = RecipeEntry>, std::io::Error> {
     let base_path: &Utf8Path = base_path
         .as_ref()
         .try_into()
         .map_err(|e: camino::FromPathError| e.into_io_error())?;
-    let walker = Walker::new(base_path, max_depth).flatten();
+    let mut walker = Walker::new(base_path, max_depth);
+    walker.set_symlinks(symlinks.clone());
+    let walker = walker.flatten();
     let grouped = group_images(walker);
     Ok(grouped.filter_map(|e| match e {
         Entry::Dir(_) => None,
@@ -381,20 +413,46 @@ pub fn all_recipes(
 }
 
 /// Walks a single directory retrieving recipes and other directories
+///
+/// Links are not followed, see [`walk_dir_with`].
 pub fn walk_dir(
     path: impl AsRef<std::path::Path>,
 ) -> Result<impl Iterator<Item = Entry>, std::io::Error> {
+    let path = path.as_ref().to_path_buf();
+    walk_dir_with(path.clone(), path, &Symlinks::default())
+}
+
+/// Like [`walk_dir`], for a dir inside `base_path`
+///
+/// The ignore files from `base_path` apply. An ignored dir, or one that
+/// escapes `base_path` through a link, is not found.
+pub fn walk_dir_with(
+    base_path: impl AsRef<std::path::Path>,
+    path: impl AsRef<std::path::Path>,
+    symlinks: &Symlinks,
+) -> Result<impl Iterator<Item = Entry>, std::io::Error> {
+    let base_path: &Utf8Path = base_path
+        .as_ref()
+        .try_into()
+        .map_err(|e: camino::FromPathError| e.into_io_error())?;
     let path: &Utf8Path = path
         .as_ref()
         .try_into()
         .map_err(|e: camino::FromPathError| e.into_io_error())?;
-    if !path.is_dir() {
+    let ignore = IgnoreStack::for_dir(base_path, path);
+    let (true, true, Some(ignore)) = (
+        path.is_dir(),
+        symlinks.is_contained(base_path, path),
+        ignore,
+    ) else {
         return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             "dir not found",
         ));
-    }
-    Ok(group_images(Walker::new(path, 0).flatten()))
+    };
+    let mut walker = Walker::new(path, 0).with_root(base_path, ignore);
+    walker.set_symlinks(symlinks.clone());
+    Ok(group_images(walker.flatten()))
 }
 
 fn group_images(walker: impl Iterator<Item = DirEntry>) -> impl Iterator<Item = Entry> {
@@ -451,6 +509,7 @@ fn try_path(
     recipe: &str,
     relative_to: Option<&Utf8Path>,
     base_path: &Utf8Path,
+    symlinks: &Symlinks,
 ) -> Result<RecipeEntry, Error> {
     let mut path = Utf8PathBuf::from(recipe).with_extension("cook");
 
@@ -469,9 +528,12 @@ fn try_path(
     }
     path = norm_path(&path);
 
-    if !path.starts_with(base_path) {
+    if !path.starts_with(base_path) || !symlinks.is_contained(base_path, &path) {
         return Err(Error::OutsideBase(recipe.to_string()));
     }
+    if is_ignored(base_path, &path) {
+        return Err(Error::NotFound(recipe.to_string()));
+    }
 
     DirEntry::new(&path)
         .map_err(Error::from)
@@ -641,6 +703,18 @@ pub struct ImageIndexes {
     step: u16,
 }
 
+impl ImageIndexes {
+    /// Index of the section in the recipe
+    pub fn section(&self) -> u16 {
+        self.section
+    }
+
+    /// Index of the step in the section, starting at 0
+    pub fn step(&self) -> u16 {
+        self.step
+    }
+}
+
 impl Image {
     fn new(recipe_name: &str, entry: DirEntry) -> Option<Self> {
         let parts = entry.file_name().rsplitn(4, '.').collect::<Vec<_>>();
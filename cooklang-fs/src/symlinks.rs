@@ -0,0 +1,57 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// How symbolic links in a collection are handled
+///
+/// By default links are not followed. When they are, a link can only point
+/// inside the collection or to one of the `allowed_targets`.
+#[derive(Debug, Clone, Default)]
+pub struct Symlinks {
+    /// Follow links to files and dirs
+    pub follow: bool,
+    /// Dirs outside the collection that links can point to
+    ///
+    /// They must be canonical, see [`Utf8Path::canonicalize_utf8`].
+    pub allowed_targets: Vec<Utf8PathBuf>,
+}
+
+impl Symlinks {
+    /// Checks that `path`, with the links resolved, does not escape the
+    /// collection
+    ///
+    /// `path` must be prefixed with `base_path`. If links are not followed,
+    /// any link under `base_path` escapes. A path that does not exist can't
+    /// escape.
+    pub fn is_contained(&self, base_path: &Utf8Path, path: &Utf8Path) -> bool {
+        let Ok(real) = path.canonicalize_utf8() else {
+            return true;
+        };
+        let Ok(base) = base_path.canonicalize_utf8() else {
+            return false;
+        };
+        if self.follow {
+            real.starts_with(&base) || self.allowed_targets.iter().any(|t| real.starts_with(t))
+        } else {
+            path.strip_prefix(base_path)
+                .is_ok_and(|rel| real == base.join(rel))
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) type FileId = (u64, u64);
+#[cfg(not(unix))]
+pub(crate) type FileId = std::path::PathBuf;
+
+/// Identifies a dir, even when reached through different links
+#[cfg(unix)]
+pub(crate) fn file_id(path: &Utf8Path) -> std::io::Result<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let m = std::fs::metadata(path)?;
+    Ok((m.dev(), m.ino()))
+}
+
+/// Identifies a dir, even when reached through different links
+#[cfg(not(unix))]
+pub(crate) fn file_id(path: &Utf8Path) -> std::io::Result<FileId> {
+    std::fs::canonicalize(path)
+}
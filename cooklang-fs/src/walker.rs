@@ -2,31 +2,54 @@ use std::{collections::VecDeque, fs::FileType};
 
 use camino::{Utf8Path, Utf8PathBuf};
 
-use crate::IMAGE_EXTENSIONS;
+use crate::{
+    ignore::IgnoreStack,
+    symlinks::{file_id, FileId, Symlinks},
+    IMAGE_EXTENSIONS,
+};
 
 /// Breadth-first, sorted by file name, .cook filtered, dir walker.
 ///
 /// Paths are relative to the base path, with the base path included. So when
 /// walking over `dir`, entries will be `dir/whatever.cook`.
 ///
-/// Files/dirs starting with '.' are ignored.
+/// Files/dirs starting with '.' are ignored, and so are the ones matched by
+/// the [`.chefignore`](crate::IGNORE_FILE) files found while walking.
+///
+/// Symbolic links are skipped unless [`Symlinks::follow`] is set. A link to
+/// one of the dirs it is in is skipped, so loops end.
 ///
 /// Currently, it returns dirs, cooklang files and images.
 #[derive(Debug)]
 pub struct Walker {
     base_path: Utf8PathBuf,
     max_depth: usize,
-    dirs: VecDeque<Utf8PathBuf>,
+    dirs: VecDeque<QueuedDir>,
     current: std::vec::IntoIter<DirEntry>,
     config_dir: Option<String>,
     ignore: Vec<String>,
+    /// Collection the walked dir is in, links can't escape it
+    root: Utf8PathBuf,
+    symlinks: Symlinks,
+}
+
+#[derive(Debug)]
+struct QueuedDir {
+    path: Utf8PathBuf,
+    ignore: IgnoreStack,
+    /// Ids of the dir and the ones it is in, only when following links
+    ancestors: Vec<FileId>,
 }
 
 impl Walker {
     pub fn new(dir: impl AsRef<Utf8Path>, max_depth: usize) -> Self {
         let dir = dir.as_ref();
         let mut dirs = VecDeque::new();
-        dirs.push_back(dir.to_path_buf());
+        dirs.push_back(QueuedDir {
+            path: dir.to_path_buf(),
+            ignore: IgnoreStack::default().push_dir(dir),
+            ancestors: Vec::new(),
+        });
         Self {
             base_path: dir.to_path_buf(),
             max_depth,
@@ -34,6 +57,8 @@ impl Walker {
             current: Vec::new().into_iter(),
             config_dir: None,
             ignore: Vec::new(),
+            root: dir.to_path_buf(),
+            symlinks: Symlinks::default(),
         }
     }
 
@@ -54,15 +79,93 @@ impl Walker {
         self.ignore.push(dir);
     }
 
-    #[tracing::instrument(level = "trace", skip(self), ret)]
-    fn process_dir(&mut self, dir: &Utf8Path) -> Result<(), std::io::Error> {
+    /// Sets how to handle symbolic links
+    pub fn set_symlinks(&mut self, symlinks: Symlinks) {
+        if let Some(front) = self.dirs.front_mut() {
+            front.ancestors.clear();
+            if symlinks.follow {
+                front.ancestors.extend(file_id(&front.path));
+            }
+        }
+        self.symlinks = symlinks;
+    }
+
+    /// Sets the collection the walked dir is in
+    ///
+    /// Used when the walk starts in a subdir of the collection, so its ignore
+    /// files apply and links can point anywhere in it.
+    pub(crate) fn with_root(mut self, root: &Utf8Path, ignore: IgnoreStack) -> Self {
+        self.root = root.to_path_buf();
+        if let Some(front) = self.dirs.front_mut() {
+            front.ignore = ignore;
+        }
+        self
+    }
+
+    /// File type of the link target, `None` if it should not be followed
+    fn follow_link(&self, path: &Utf8Path) -> Option<FileType> {
+        if !self.symlinks.follow {
+            tracing::trace!("skipping link '{path}'");
+            return None;
+        }
+        let metadata = match path.metadata() {
+            Ok(m) => m,
+            Err(err) => {
+                tracing::warn!("Skipping broken link '{path}': {err}");
+                return None;
+            }
+        };
+        if !self.symlinks.is_contained(&self.root, path) {
+            tracing::warn!("Skipping link '{path}', it points outside the collection");
+            return None;
+        }
+        Some(metadata.file_type())
+    }
+
+    /// Ids of `dir` and the ones it is in, `None` if it is one of them
+    ///
+    /// The same dir can be reached through different links, it's only a loop
+    /// when it is inside itself.
+    fn enter(&self, dir: &Utf8Path, ancestors: &[FileId]) -> Option<Vec<FileId>> {
+        if !self.symlinks.follow {
+            return Some(Vec::new());
+        }
+        match file_id(dir) {
+            Ok(id) if ancestors.contains(&id) => {
+                tracing::warn!("Skipping '{dir}', it links to a dir it is in");
+                None
+            }
+            Ok(id) => {
+                let mut ancestors = ancestors.to_vec();
+                ancestors.push(id);
+                Some(ancestors)
+            }
+            Err(err) => {
+                tracing::warn!("Skipping '{dir}': {err}");
+                None
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(dir = %queued.path), ret)]
+    fn process_dir(&mut self, queued: &QueuedDir) -> Result<(), std::io::Error> {
+        let QueuedDir {
+            path: dir,
+            ignore,
+            ancestors,
+        } = queued;
         // the entire dir needs to be processed as one because entry order
         // is not guaranteed, so we need to sort
-        let mut new_dirs = Vec::new();
         let mut new_entries = Vec::new();
         for e in dir.read_dir_utf8()? {
             let e = e?;
-            let ft = e.file_type()?;
+            let mut ft = e.file_type()?;
+            if ft.is_symlink() {
+                match self.follow_link(e.path()) {
+                    Some(target) => ft = target,
+                    None => continue,
+                }
+            }
 
             // print warning for unexpected config dir
             if let Some(config_dir) = &self.config_dir {
@@ -79,29 +182,46 @@ impl Walker {
                 continue;
             }
 
+            if ignore.is_ignored(e.path(), ft.is_dir()) {
+                continue;
+            }
+
             let entry = DirEntry {
                 path: e.into_path(),
                 file_type: ft,
             };
 
-            if entry.file_type.is_dir() {
-                let depth = entry_depth(entry.path(), &self.base_path);
-                if depth <= self.max_depth {
-                    new_dirs.push(entry.path().to_path_buf());
-                }
-            } else if !(entry.is_cooklang_file() || entry.is_image()) {
+            if !(entry.file_type.is_dir() || entry.is_cooklang_file() || entry.is_image()) {
                 continue;
             }
             new_entries.push(entry);
         }
-        new_dirs.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
         new_entries.sort_by(|a, b| {
             a.file_type
                 .is_dir()
                 .cmp(&b.file_type.is_dir())
                 .then_with(|| a.file_name().cmp(b.file_name()))
         });
-        self.dirs.extend(new_dirs);
+        // after sorting, so the same link is always the one skipped
+        new_entries.retain(|entry| {
+            if !entry.file_type.is_dir() {
+                return true;
+            }
+            let Some(ancestors) = self.enter(entry.path(), ancestors) else {
+                return false;
+            };
+            let depth = entry_depth(entry.path(), &self.base_path);
+            if depth <= self.max_depth {
+                let path = entry.path().to_path_buf();
+                let ignore = ignore.push_dir(&path);
+                self.dirs.push_back(QueuedDir {
+                    path,
+                    ignore,
+                    ancestors,
+                });
+            }
+            true
+        });
         self.current = new_entries.into_iter();
         Ok(())
     }
@@ -181,3 +301,35 @@ fn entry_depth(path: &Utf8Path, base_path: &Utf8Path) -> usize {
         .components()
         .count()
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_links_to_the_same_dir() {
+        let base = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("cooklang-fs-walker-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        std::fs::write(base.join("shared/soup.cook"), "").unwrap();
+        std::os::unix::fs::symlink("shared", base.join("a")).unwrap();
+        std::os::unix::fs::symlink("shared", base.join("b")).unwrap();
+        std::os::unix::fs::symlink("..", base.join("shared/up")).unwrap();
+
+        let mut walker = Walker::new(&base, 10);
+        walker.set_symlinks(Symlinks {
+            follow: true,
+            allowed_targets: Vec::new(),
+        });
+        let recipes = walker
+            .map(Result::unwrap)
+            .filter(DirEntry::is_cooklang_file)
+            .map(|e| e.path().strip_prefix(&base).unwrap().to_string())
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(recipes, ["a/soup.cook", "b/soup.cook", "shared/soup.cook"]);
+    }
+}
@@ -1,4 +1,9 @@
 //! Format a recipe as cooklang
+//!
+//! To edit or format recipe files keeping comments and the original spelling,
+//! see [`LosslessRecipe`].
+
+mod lossless;
 
 use std::{fmt::Write, io};
 
@@ -11,6 +16,8 @@ use cooklang::{
 };
 use regex::Regex;
 
+pub use lossless::{trim_steps, Component, ComponentKind, LosslessRecipe, ParseError};
+
 pub fn print_cooklang<D, V: QuantityValue>(
     recipe: &Recipe<D, V>,
     mut writer: impl io::Write,
@@ -18,7 +25,9 @@ pub fn print_cooklang<D, V: QuantityValue>(
     let w = &mut writer;
 
     metadata(w, &recipe.metadata)?;
-    writeln!(w)?;
+    if !recipe.metadata.map.is_empty() {
+        writeln!(w)?;
+    }
     sections(w, recipe)?;
 
     Ok(())
@@ -187,12 +196,6 @@ struct ComponentFormatter<'a, V: QuantityValue> {
     note: Option<&'a str>,
 }
 
-enum ComponentKind {
-    Ingredient,
-    Cookware,
-    Timer,
-}
-
 impl<'a, V: QuantityValue> ComponentFormatter<'a, V> {
     fn format(self, w: &mut String) {
         w.push(match self.kind {
@@ -209,14 +212,14 @@ impl<'a, V: QuantityValue> ComponentFormatter<'a, V> {
                 Modifiers::NEW => '+',
                 _ => panic!("Unknown modifier: {:?}", m),
             });
-            if m == Modifiers::REF && self.intermediate_data.is_some() {
+            if let Some(data) = self.intermediate_data.filter(|_| m == Modifiers::REF) {
                 use cooklang::parser::IntermediateRefMode::*;
                 use cooklang::parser::IntermediateTargetKind::*;
                 let IntermediateData {
                     ref_mode,
                     target_kind,
                     val,
-                } = self.intermediate_data.unwrap();
+                } = data;
                 let repr = match (target_kind, ref_mode) {
                     (Step, Number) => format!("{val}"),
                     (Step, Relative) => format!("~{val}"),
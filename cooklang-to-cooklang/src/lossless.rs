@@ -0,0 +1,601 @@
+//! Lossless editing and formatting of cooklang source
+//!
+//! [`print_cooklang`](crate::print_cooklang) writes a recipe from the analyzed
+//! [`Recipe`](cooklang::Recipe), so everything the analysis discards, like
+//! comments or the original spelling of the units, is lost. [`LosslessRecipe`]
+//! works on the source text instead. Only metadata entries and components are
+//! parsed, everything else is kept as is.
+
+use std::{fmt, ops::Range};
+
+use cooklang::{
+    error::SourceDiag,
+    parser::{Event, PullParser, QuantityValue},
+    Extensions, Text,
+};
+
+/// A recipe that can be edited and written back without losing anything
+///
+/// Printing it with [`Display`](fmt::Display) gives back the exact input if
+/// nothing was changed. Only the edited parts are written again.
+#[derive(Debug, Clone)]
+pub struct LosslessRecipe<'i> {
+    src: &'i str,
+    nodes: Vec<Node>,
+    new_metadata: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    span: Range<usize>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Metadata(MetadataEntry),
+    Component(Component),
+}
+
+#[derive(Debug, Clone)]
+struct MetadataEntry {
+    key: String,
+    value: String,
+    /// Source from the start of the line to the value
+    prefix: Range<usize>,
+    /// Source from the value to the end of the line, without the line break
+    suffix: Range<usize>,
+    edited: bool,
+    removed: bool,
+}
+
+/// Type of a [`Component`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    Ingredient,
+    Cookware,
+    Timer,
+}
+
+/// An ingredient, cookware or timer in a [`LosslessRecipe`]
+#[derive(Debug, Clone)]
+pub struct Component {
+    kind: ComponentKind,
+    modifiers: String,
+    name: Option<String>,
+    alias: Option<String>,
+    quantity: Option<String>,
+    note: Option<String>,
+    braces: bool,
+    /// Source of the name, to edit it in components with comments
+    name_span: Option<Range<usize>>,
+    has_comments: bool,
+    edited: bool,
+}
+
+/// Error from [`LosslessRecipe::parse`]
+#[derive(Debug)]
+pub struct ParseError(SourceDiag);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error parsing recipe: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl<'i> LosslessRecipe<'i> {
+    /// Parses the source of a recipe
+    ///
+    /// Only parser errors are checked, not the ones from the analysis. The
+    /// extensions should be the same used to parse the full recipe.
+    pub fn parse(src: &'i str, extensions: Extensions) -> Result<Self, ParseError> {
+        let mut nodes = Vec::new();
+        for event in PullParser::new(src, extensions) {
+            let node = match event {
+                Event::Error(diag) => return Err(ParseError(diag)),
+                Event::Metadata { key, value } => metadata_node(src, &key, &value),
+                Event::Ingredient(igr) => {
+                    let span = igr.span().range();
+                    let igr = igr.into_inner();
+                    let quantity = igr
+                        .quantity
+                        .map(|q| quantity_str(src, &q.value, q.unit.as_ref()));
+                    component_node(
+                        src,
+                        span,
+                        ComponentKind::Ingredient,
+                        Some(&igr.name),
+                        igr.alias.as_ref(),
+                        quantity,
+                        igr.note.as_ref(),
+                    )
+                }
+                Event::Cookware(cw) => {
+                    let span = cw.span().range();
+                    let cw = cw.into_inner();
+                    let quantity = cw.quantity.map(|q| quantity_str(src, &q, None));
+                    component_node(
+                        src,
+                        span,
+                        ComponentKind::Cookware,
+                        Some(&cw.name),
+                        cw.alias.as_ref(),
+                        quantity,
+                        cw.note.as_ref(),
+                    )
+                }
+                Event::Timer(t) => {
+                    let span = t.span().range();
+                    let t = t.into_inner();
+                    let quantity = t
+                        .quantity
+                        .map(|q| quantity_str(src, &q.value, q.unit.as_ref()));
+                    component_node(
+                        src,
+                        span,
+                        ComponentKind::Timer,
+                        t.name.as_ref(),
+                        None,
+                        quantity,
+                        None,
+                    )
+                }
+                _ => continue,
+            };
+            nodes.push(node);
+        }
+        nodes.sort_by_key(|n| n.span.start);
+        Ok(Self {
+            src,
+            nodes,
+            new_metadata: Vec::new(),
+        })
+    }
+
+    /// Iterate over the metadata entries in source order
+    pub fn metadata(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.metadata_entries()
+            .map(|m| (m.key.as_str(), m.value.as_str()))
+            .chain(
+                self.new_metadata
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str())),
+            )
+    }
+
+    /// Get the value of a metadata entry
+    ///
+    /// If the key is repeated, the last value is returned, like the parser
+    /// does.
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata()
+            .filter(|(k, _)| *k == key)
+            .last()
+            .map(|(_, v)| v)
+    }
+
+    /// Sets the value of a metadata entry
+    ///
+    /// If the key exists, the value is replaced in place. If not, a new entry
+    /// is added after the last one.
+    pub fn set_metadata(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        if let Some((_, v)) = self.new_metadata.iter_mut().find(|(k, _)| k == key) {
+            *v = value;
+            return;
+        }
+        let mut found = false;
+        for entry in self.metadata_entries_mut().filter(|m| m.key == key) {
+            entry.value.clone_from(&value);
+            entry.edited = true;
+            found = true;
+        }
+        if !found {
+            self.new_metadata.push((key.to_string(), value));
+        }
+    }
+
+    /// Removes a metadata entry, returns true if it existed
+    ///
+    /// The whole line of the entry is removed.
+    pub fn remove_metadata(&mut self, key: &str) -> bool {
+        let before = self.new_metadata.len();
+        self.new_metadata.retain(|(k, _)| k != key);
+        let mut found = before != self.new_metadata.len();
+        for entry in self.metadata_entries_mut().filter(|m| m.key == key) {
+            entry.removed = true;
+            found = true;
+        }
+        found
+    }
+
+    /// Iterate over the components in source order
+    pub fn components(&self) -> impl Iterator<Item = &Component> {
+        self.nodes.iter().filter_map(|n| match &n.kind {
+            NodeKind::Component(c) => Some(c),
+            _ => None,
+        })
+    }
+
+    /// Iterate mutably over the components in source order
+    pub fn components_mut(&mut self) -> impl Iterator<Item = &mut Component> {
+        self.nodes.iter_mut().filter_map(|n| match &mut n.kind {
+            NodeKind::Component(c) => Some(c),
+            _ => None,
+        })
+    }
+
+    /// Returns true if anything has been edited
+    pub fn is_edited(&self) -> bool {
+        !self.new_metadata.is_empty()
+            || self.nodes.iter().any(|n| match &n.kind {
+                NodeKind::Metadata(m) => m.edited || m.removed,
+                NodeKind::Component(c) => c.edited,
+            })
+    }
+
+    /// Writes the recipe in a canonical form
+    ///
+    /// Unlike [`print_cooklang`](crate::print_cooklang) this keeps comments,
+    /// paragraphs, the order of the metadata and the original spelling of
+    /// values and units. What changes is:
+    /// - Metadata entries are written as `>> key: value`.
+    /// - Components are written without extra spaces and with the `%` unit
+    ///   separator. Components with comments inside are left untouched.
+    /// - Trailing whitespace is removed and consecutive blank lines are
+    ///   collapsed into one.
+    /// - `\r\n` line breaks are converted to `\n`.
+    pub fn formatted(&self) -> String {
+        let mut out = String::with_capacity(self.src.len());
+        self.write(&mut out, true);
+
+        let mut formatted = String::with_capacity(out.len());
+        let mut blank = true; // no leading blank lines
+        for line in out.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                if blank {
+                    continue;
+                }
+                blank = true;
+            } else {
+                blank = false;
+            }
+            formatted.push_str(line);
+            formatted.push('\n');
+        }
+        if blank {
+            formatted.truncate(formatted.trim_end().len());
+            if !formatted.is_empty() {
+                formatted.push('\n');
+            }
+        }
+        formatted
+    }
+
+    fn write(&self, w: &mut String, format: bool) {
+        let mut last = 0;
+        let mut new_metadata_written = self.new_metadata.is_empty();
+        let last_metadata = self
+            .nodes
+            .iter()
+            .rposition(|n| matches!(n.kind, NodeKind::Metadata(_)));
+
+        if last_metadata.is_none() && !new_metadata_written {
+            self.write_new_metadata(w);
+            if !self.src.trim().is_empty() && !self.src.starts_with(['\n', '\r']) {
+                w.push('\n');
+            }
+            new_metadata_written = true;
+        }
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            w.push_str(&self.src[last..node.span.start]);
+            last = node.span.end;
+            match &node.kind {
+                NodeKind::Metadata(m) if m.removed => {
+                    // remove the line break too
+                    let rest = &self.src[last..];
+                    if rest.starts_with("\r\n") {
+                        last += 2;
+                    } else if rest.starts_with('\n') {
+                        last += 1;
+                    }
+                }
+                NodeKind::Metadata(m) => {
+                    if m.edited || format {
+                        m.render(self.src, w, format);
+                    } else {
+                        w.push_str(&self.src[node.span.clone()]);
+                    }
+                }
+                NodeKind::Component(c) => {
+                    if c.edited && c.has_comments {
+                        c.render_name(self.src, node.span.clone(), w);
+                    } else if c.edited || (format && !c.has_comments) {
+                        c.render(w);
+                    } else {
+                        w.push_str(&self.src[node.span.clone()]);
+                    }
+                }
+            }
+
+            if !new_metadata_written && Some(index) == last_metadata {
+                if matches!(&node.kind, NodeKind::Metadata(m) if m.removed) {
+                    self.write_new_metadata(w);
+                    new_metadata_written = true;
+                    continue;
+                }
+                let line_break = self.src[last..]
+                    .find('\n')
+                    .filter(|&i| self.src[last..last + i].trim().is_empty());
+                match line_break {
+                    Some(i) => {
+                        w.push_str(&self.src[last..last + i + 1]);
+                        last += i + 1;
+                    }
+                    None => w.push('\n'),
+                }
+                self.write_new_metadata(w);
+                new_metadata_written = true;
+            }
+        }
+        w.push_str(&self.src[last..]);
+    }
+
+    fn write_new_metadata(&self, w: &mut String) {
+        for (key, value) in &self.new_metadata {
+            w.push_str(&format!(">> {key}: {value}\n"));
+        }
+    }
+
+    fn metadata_entries(&self) -> impl Iterator<Item = &MetadataEntry> {
+        self.nodes.iter().filter_map(|n| match &n.kind {
+            NodeKind::Metadata(m) if !m.removed => Some(m),
+            _ => None,
+        })
+    }
+
+    fn metadata_entries_mut(&mut self) -> impl Iterator<Item = &mut MetadataEntry> {
+        self.nodes.iter_mut().filter_map(|n| match &mut n.kind {
+            NodeKind::Metadata(m) if !m.removed => Some(m),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for LosslessRecipe<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::with_capacity(self.src.len());
+        self.write(&mut out, false);
+        f.write_str(&out)
+    }
+}
+
+/// Removes the whitespace at the start and end of steps and text blocks
+///
+/// [`LosslessRecipe::formatted`] trims lines, and that's the only change that
+/// doesn't affect the recipe. Compare two parsed recipes after this to know if
+/// formatting changed the meaning.
+pub fn trim_steps<D, V: cooklang::quantity::QuantityValue>(
+    mut recipe: cooklang::Recipe<D, V>,
+) -> cooklang::Recipe<D, V> {
+    use cooklang::{model::Item, Content};
+
+    for section in &mut recipe.sections {
+        for content in &mut section.content {
+            match content {
+                Content::Step(step) => {
+                    if let Some(Item::Text { value }) = step.items.first_mut() {
+                        *value = value.trim_start().to_string();
+                    }
+                    if let Some(Item::Text { value }) = step.items.last_mut() {
+                        *value = value.trim_end().to_string();
+                    }
+                    step.items
+                        .retain(|i| !matches!(i, Item::Text { value } if value.is_empty()));
+                }
+                Content::Text(text) => *text = text.trim().to_string(),
+            }
+        }
+    }
+    recipe
+}
+
+impl MetadataEntry {
+    fn render(&self, src: &str, w: &mut String, format: bool) {
+        let suffix = &src[self.suffix.clone()];
+        if format {
+            w.push_str(&format!(">> {}: {}", self.key, self.value));
+            if !suffix.trim().is_empty() {
+                w.push(' ');
+                w.push_str(suffix.trim());
+            }
+        } else {
+            let prefix = &src[self.prefix.clone()];
+            if self.value.is_empty() || !prefix.ends_with(char::is_whitespace) {
+                w.push_str(prefix.trim_end());
+                w.push(' ');
+            } else {
+                w.push_str(prefix);
+            }
+            w.push_str(&self.value);
+            w.push_str(suffix);
+        }
+    }
+}
+
+impl Component {
+    pub fn kind(&self) -> ComponentKind {
+        self.kind
+    }
+
+    /// Modifiers as written in the source, including intermediate references
+    pub fn modifiers(&self) -> &str {
+        &self.modifiers
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// Quantity as written in the source, without the braces
+    ///
+    /// Spaces are removed and the unit is separated with `%`.
+    pub fn quantity(&self) -> Option<&str> {
+        self.quantity.as_deref()
+    }
+
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// Changes the name of the component
+    ///
+    /// Everything else is kept, comments in the old name are moved after the
+    /// new one.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = Some(name.into());
+        self.edited = true;
+    }
+
+    /// Writes the source with only the name replaced, keeping the comments
+    fn render_name(&self, src: &str, span: Range<usize>, w: &mut String) {
+        // timers can have no name, it goes after the sigil
+        let at = span.start + 1 + self.modifiers.len();
+        let name_span = self.name_span.clone().unwrap_or(at..at);
+        w.push_str(&src[span.start..name_span.start]);
+        let name = self.name.as_deref().unwrap_or_default();
+        w.push_str(name);
+        let mut rest = &src[name_span.clone()];
+        while let Some(start) = rest.find("[-") {
+            let end = rest[start..]
+                .find("-]")
+                .map_or(rest.len(), |i| start + i + 2);
+            w.push(' ');
+            w.push_str(&rest[start..end]);
+            rest = &rest[end..];
+        }
+        let multi_word = name.chars().any(|c| !c.is_alphanumeric());
+        if multi_word && !self.braces && self.alias.is_none() {
+            w.push_str("{}");
+        }
+        w.push_str(&src[name_span.end..span.end]);
+    }
+
+    fn render(&self, w: &mut String) {
+        w.push(match self.kind {
+            ComponentKind::Ingredient => '@',
+            ComponentKind::Cookware => '#',
+            ComponentKind::Timer => '~',
+        });
+        w.push_str(&self.modifiers);
+        let mut multi_word = false;
+        if let Some(name) = &self.name {
+            multi_word = name.chars().any(|c| !c.is_alphanumeric());
+            w.push_str(name);
+            if let Some(alias) = &self.alias {
+                multi_word = true;
+                w.push('|');
+                w.push_str(alias);
+            }
+        }
+        if let Some(q) = &self.quantity {
+            w.push('{');
+            w.push_str(q);
+            w.push('}');
+        } else if multi_word || self.braces {
+            w.push_str("{}");
+        }
+        if let Some(note) = &self.note {
+            w.push('(');
+            w.push_str(note);
+            w.push(')');
+        }
+    }
+}
+
+fn metadata_node(src: &str, key: &Text, value: &Text) -> Node {
+    let key_span = key.span();
+    let value_span = value.span();
+    let start = src[..key_span.start()].rfind('\n').map_or(0, |i| i + 1);
+    let end = src[value_span.end()..]
+        .find(['\r', '\n'])
+        .map_or(src.len(), |i| value_span.end() + i);
+    // whitespace around the value belongs to the prefix and suffix
+    let raw = &src[value_span.range()];
+    let value_start = value_span.end() - raw.trim_start().len();
+    let value_end = value_span.start() + raw.trim_end().len();
+    let value_end = value_end.max(value_start);
+    Node {
+        span: start..end,
+        kind: NodeKind::Metadata(MetadataEntry {
+            key: key.text_outer_trimmed().into_owned(),
+            value: value.text_outer_trimmed().into_owned(),
+            prefix: start..value_start,
+            suffix: value_end..end,
+            edited: false,
+            removed: false,
+        }),
+    }
+}
+
+fn component_node(
+    src: &str,
+    span: Range<usize>,
+    kind: ComponentKind,
+    name: Option<&Text>,
+    alias: Option<&Text>,
+    quantity: Option<String>,
+    note: Option<&Text>,
+) -> Node {
+    let raw = &src[span.clone()];
+    // the sigil is always 1 byte
+    let modifiers = name.map_or("", |n| &src[span.start + 1..n.span().start()]);
+    let after_name = alias.or(name).map_or(span.start + 1, |t| t.span().end());
+    let braces = src[after_name..span.end].starts_with('{');
+    Node {
+        span,
+        kind: NodeKind::Component(Component {
+            kind,
+            modifiers: modifiers.to_string(),
+            name: name.map(|t| t.text_trimmed().into_owned()),
+            alias: alias.map(|t| t.text_trimmed().into_owned()),
+            quantity,
+            note: note.map(|t| t.text_outer_trimmed().into_owned()),
+            braces,
+            name_span: name.map(|n| n.span().range()),
+            has_comments: raw.contains("--") || raw.contains("[-"),
+            edited: false,
+        }),
+    }
+}
+
+fn quantity_str(src: &str, value: &QuantityValue, unit: Option<&Text>) -> String {
+    let raw = |v: &cooklang::Located<cooklang::quantity::Value>| src[v.span().range()].trim();
+    let mut s = match value {
+        QuantityValue::Single { value, auto_scale } => {
+            let mut s = raw(value).to_string();
+            if auto_scale.is_some() {
+                s.push('*');
+            }
+            s
+        }
+        QuantityValue::Many(values) => values.iter().map(raw).collect::<Vec<_>>().join("|"),
+    };
+    if let Some(unit) = unit {
+        s.push('%');
+        s.push_str(&unit.text_trimmed());
+    }
+    s
+}
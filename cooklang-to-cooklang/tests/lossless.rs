@@ -0,0 +1,192 @@
+//! Round trip properties of [`LosslessRecipe`] over the recipes in `corpus/`
+
+use std::path::Path;
+
+use cooklang::{Converter, CooklangParser, Extensions, ScalableRecipe};
+use cooklang_to_cooklang::{trim_steps, LosslessRecipe};
+
+fn corpus() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "cook"))
+        .map(|p| {
+            let name = p.file_name().unwrap().to_string_lossy().into_owned();
+            let src = std::fs::read_to_string(&p).unwrap();
+            (name, src)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    assert!(!files.is_empty(), "empty corpus");
+    files
+}
+
+const EXTENSIONS: &[Extensions] = &[
+    Extensions::all(),
+    Extensions::all().difference(Extensions::MULTILINE_STEPS),
+    Extensions::COMPAT,
+];
+
+fn parse(src: &str, extensions: Extensions) -> ScalableRecipe {
+    let parser = CooklangParser::new(extensions, Converter::bundled());
+    let (recipe, _) = parser.parse(src).into_result().unwrap();
+    trim_steps(recipe)
+}
+
+fn comments(src: &str) -> Vec<&str> {
+    let mut comments = Vec::new();
+    let mut rest = src;
+    while let Some(start) = rest.find(['-', '[']) {
+        rest = &rest[start..];
+        if rest.starts_with("--") {
+            let end = rest.find(['\r', '\n']).unwrap_or(rest.len());
+            comments.push(rest[..end].trim_end());
+            rest = &rest[end..];
+        } else if rest.starts_with("[-") {
+            let end = rest.find("-]").map_or(rest.len(), |i| i + 2);
+            comments.push(&rest[..end]);
+            rest = &rest[end..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    comments
+}
+
+#[test]
+fn printing_without_changes_is_identical() {
+    for (name, src) in corpus() {
+        for &ext in EXTENSIONS {
+            let recipe = LosslessRecipe::parse(&src, ext).unwrap();
+            assert!(!recipe.is_edited());
+            assert_eq!(recipe.to_string(), src, "{name} with {ext:?}");
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_the_recipe() {
+    for (name, src) in corpus() {
+        for &ext in EXTENSIONS {
+            let formatted = LosslessRecipe::parse(&src, ext).unwrap().formatted();
+            assert_eq!(
+                parse(&formatted, ext),
+                parse(&src, ext),
+                "{name} with {ext:?}:\n{formatted}"
+            );
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_comments() {
+    for (name, src) in corpus() {
+        let formatted = LosslessRecipe::parse(&src, Extensions::all())
+            .unwrap()
+            .formatted();
+        for comment in comments(&src) {
+            assert!(
+                formatted.contains(comment),
+                "{name}: missing comment {comment:?}\n{formatted}"
+            );
+        }
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for (name, src) in corpus() {
+        for &ext in EXTENSIONS {
+            let once = LosslessRecipe::parse(&src, ext).unwrap().formatted();
+            let twice = LosslessRecipe::parse(&once, ext).unwrap().formatted();
+            assert_eq!(once, twice, "{name} with {ext:?}");
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_units_and_metadata_order() {
+    let src = ">>b: 2\n>> a:1\n\nAdd @milk{1/2 % cups} and @salt{1 pinch}.\n";
+    let formatted = LosslessRecipe::parse(src, Extensions::all())
+        .unwrap()
+        .formatted();
+    assert_eq!(
+        formatted,
+        ">> b: 2\n>> a: 1\n\nAdd @milk{1/2%cups} and @salt{1%pinch}.\n"
+    );
+}
+
+#[test]
+fn edits_only_change_the_edited_parts() {
+    for (name, src) in corpus() {
+        let ext = Extensions::all();
+        let mut recipe = LosslessRecipe::parse(&src, ext).unwrap();
+        for c in recipe.components_mut() {
+            if let Some(n) = c.name().map(|n| n.to_uppercase()) {
+                c.set_name(n);
+            }
+        }
+        recipe.set_metadata("source", "my book");
+        recipe.remove_metadata("tags");
+        recipe.set_metadata("new key", "new value");
+        let edited = recipe.to_string();
+
+        let original = parse(&src, ext);
+        let mut expected = original.clone();
+        for igr in &mut expected.ingredients {
+            igr.name = igr.name.to_uppercase();
+        }
+        let edited = parse(&edited, ext);
+        let names = |r: &ScalableRecipe| {
+            r.ingredients
+                .iter()
+                .map(|i| (i.name.clone(), i.quantity.clone(), i.modifiers()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&edited), names(&expected), "{name}");
+        assert_eq!(edited.sections.len(), original.sections.len(), "{name}");
+        assert_eq!(
+            edited.metadata.map.get("source").unwrap(),
+            "my book",
+            "{name}"
+        );
+        assert_eq!(edited.metadata.map.get("new key").unwrap(), "new value");
+        assert!(!edited.metadata.map.contains_key("tags"), "{name}");
+    }
+}
+
+#[test]
+fn edits_keep_comments() {
+    for (name, src) in corpus() {
+        let mut recipe = LosslessRecipe::parse(&src, Extensions::all()).unwrap();
+        for c in recipe.components_mut() {
+            if let Some(n) = c.name().map(|n| format!("new {n}")) {
+                c.set_name(n);
+            }
+        }
+        let edited = recipe.to_string();
+        for comment in comments(&src) {
+            assert!(
+                edited.contains(comment),
+                "{name}: missing comment {comment:?}\n{edited}"
+            );
+        }
+    }
+}
+
+#[test]
+fn edit_in_place() {
+    let src = "-- comment\n>> tags: a, b -- keep\n>> servings: 2\n\nFry @garlic clove{2}(sliced) in #pan.\n";
+    let mut recipe = LosslessRecipe::parse(src, Extensions::all()).unwrap();
+    recipe.set_metadata("tags", "a, c");
+    recipe
+        .components_mut()
+        .find(|c| c.name() == Some("garlic clove"))
+        .unwrap()
+        .set_name("garlic");
+    assert_eq!(
+        recipe.to_string(),
+        "-- comment\n>> tags: a, c -- keep\n>> servings: 2\n\nFry @garlic{2}(sliced) in #pan.\n"
+    );
+}
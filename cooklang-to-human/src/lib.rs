@@ -68,7 +68,7 @@ fn tag_color(tag: &str) -> owo_colors::AnsiColors {
         .enumerate()
         .map(|(i, c)| c as usize * i)
         .reduce(usize::wrapping_add)
-        .map(|h| (h % 7))
+        .map(|h| h % 7)
         .unwrap_or_default();
     match hash {
         0 => owo_colors::AnsiColors::Red,
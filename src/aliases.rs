@@ -0,0 +1,118 @@
+//! Ingredient synonyms
+//!
+//! The aliases file has one group of names per line separated by `=`. The
+//! first name is the one used when merging ingredients. Empty lines and lines
+//! starting with `#` are ignored.
+//!
+//! ```text
+//! # onions
+//! green onion = scallion = spring onion
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use cooklang::aisle::AisleConf;
+
+#[derive(Debug, Default, Clone)]
+pub struct Aliases {
+    /// Lower case name to its group in `groups`
+    index: HashMap<String, usize>,
+    /// Names of each group, the first one is the canonical
+    groups: Vec<Vec<String>>,
+}
+
+impl Aliases {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut aliases = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let names = line
+                .split('=')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if names.len() < 2 {
+                bail!(
+                    "Line {}: expected at least two names separated by '='",
+                    n + 1
+                );
+            }
+            let group = aliases.groups.len();
+            for name in &names {
+                if aliases.index.insert(name.to_lowercase(), group).is_some() {
+                    bail!("Line {}: '{name}' is already in another group", n + 1);
+                }
+            }
+            aliases.groups.push(names);
+        }
+        Ok(aliases)
+    }
+
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read aliases file '{path}'"))?;
+        Self::parse(&text).with_context(|| format!("Error parsing aliases file '{path}'"))
+    }
+
+    fn group(&self, name: &str) -> Option<&[String]> {
+        let i = *self.index.get(&name.trim().to_lowercase())?;
+        Some(&self.groups[i])
+    }
+
+    /// Name used for all the synonyms of `name`, or `name` if it has none
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        self.group(name).map(|g| g[0].as_str()).unwrap_or(name)
+    }
+
+    /// All the names of the group of `name`, including itself
+    ///
+    /// Empty if `name` has no synonyms.
+    pub fn synonyms(&self, name: &str) -> &[String] {
+        self.group(name).unwrap_or_default()
+    }
+
+    /// Adds the synonyms of the ingredients in the aisle conf
+    ///
+    /// This way any of the names is found in the same category.
+    pub fn extend_aisle<'a>(&'a self, aisle: &mut AisleConf<'a>) {
+        for igr in aisle.categories.iter_mut().flat_map(|c| &mut c.ingredients) {
+            let mut extra = Vec::new();
+            for name in &igr.names {
+                for s in self.synonyms(name) {
+                    if !igr.names.contains(&s.as_str()) && !extra.contains(&s.as_str()) {
+                        extra.push(s.as_str());
+                    }
+                }
+            }
+            igr.names.extend(extra);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_lookup() {
+        let aliases = Aliases::parse(
+            "# comment\n\nGreen onion = scallion = spring onion\ncilantro=coriander\n",
+        )
+        .unwrap();
+        assert_eq!(aliases.canonical("Scallion"), "Green onion");
+        assert_eq!(aliases.canonical("coriander"), "cilantro");
+        assert_eq!(aliases.canonical("garlic"), "garlic");
+        assert_eq!(aliases.canonical("spring onion"), "Green onion");
+        assert_eq!(aliases.synonyms("cilantro").len(), 2);
+        assert!(aliases.synonyms("garlic").is_empty());
+
+        assert!(Aliases::parse("lonely").is_err());
+        assert!(Aliases::parse("a = b\nb = c").is_err());
+    }
+}
@@ -3,8 +3,8 @@ use clap::{Args, Parser, Subcommand};
 use cooklang::Extensions;
 
 use crate::cmd::{
-    collection, config, convert, edit, generate_completions, list, new, recipe, shopping_list,
-    units,
+    collection, config, convert, edit, fmt, generate_completions, ingredients, list, mv, new,
+    recipe, rm, shopping_list, stats, suggest, tags, trash, units,
 };
 
 #[cfg(feature = "serve")]
@@ -54,6 +54,22 @@ pub enum Command {
     New(new::NewArgs),
     /// Edit an existing recipe
     Edit(edit::EditArgs),
+    /// Format recipes in place
+    Fmt(fmt::FmtArgs),
+    /// Move or rename a recipe, updating the references to it
+    Mv(mv::MvArgs),
+    /// Remove a recipe and its images, moving them to the trash
+    Rm(rm::RmArgs),
+    /// Manage removed recipes
+    Trash(trash::TrashArgs),
+    /// Show statistics about the collection
+    Stats(stats::StatsArgs),
+    /// Manage the tags of the collection
+    Tags(tags::TagsArgs),
+    /// Manage the ingredients of the collection
+    Ingredients(ingredients::IngredientsArgs),
+    /// Recipes you can cook with what is in the pantry
+    Suggest(suggest::SuggestArgs),
 }
 
 #[derive(Debug, Args)]
@@ -0,0 +1,347 @@
+//! On disk cache of the parsed recipes in `.cooklang/cache`
+//!
+//! Each recipe is stored with its modification time and size to know when it
+//! has to be parsed again. The whole cache is discarded when the parser
+//! config (extensions and units) or chef itself change.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{Hash, Hasher},
+    time::SystemTime,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{error::Severity, Content, CooklangParser, Metadata, ParseOptions};
+use cooklang_fs::RecipeEntry;
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher13;
+
+use crate::{
+    config::Config,
+    util::{
+        is_recipe_ref, metadata_validator, par_map, required_cookware, required_ingredients,
+        write_atomic,
+    },
+    COOK_DIR,
+};
+
+pub const CACHE_DIR: &str = "cache";
+const CACHE_FILE: &str = "recipes.json";
+
+/// What chef needs to know about a recipe without parsing it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeData {
+    /// `None` if the recipe has errors
+    pub metadata: Option<Metadata>,
+    pub ingredients: Vec<String>,
+    pub cookware: Vec<String>,
+    /// Ingredients without the optional ones and references
+    pub required_ingredients: Vec<String>,
+    pub required_cookware: Vec<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    /// The recipe references other recipes
+    ///
+    /// References are not checked here because they depend on other files.
+    pub has_references: bool,
+    /// Names of the ingredients that are recipe references
+    pub references: Vec<String>,
+    /// Number of steps, without the text blocks
+    pub steps: usize,
+    /// Modification time of the file, not saved
+    ///
+    /// Only the server index sets it, from [`RecipeCache::modified`].
+    #[serde(skip)]
+    pub modified: Option<SystemTime>,
+}
+
+impl RecipeData {
+    pub fn parse(text: &str, parser: &CooklangParser) -> Self {
+        let options = ParseOptions {
+            recipe_ref_check: None,
+            metadata_validator: Some(Box::new(metadata_validator)),
+        };
+        let res = parser.parse_with_options(text, options);
+        let (mut errors, mut warnings) = (0, 0);
+        for err in res.report().iter() {
+            match err.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+
+        let mut data = Self {
+            metadata: None,
+            ingredients: Vec::new(),
+            cookware: Vec::new(),
+            required_ingredients: Vec::new(),
+            required_cookware: Vec::new(),
+            errors,
+            warnings,
+            has_references: false,
+            references: Vec::new(),
+            steps: 0,
+            modified: None,
+        };
+        if let Some(r) = res.valid_output() {
+            data.metadata = Some(r.metadata.to_owned());
+            data.ingredients = r.ingredients.iter().map(|i| i.name.to_owned()).collect();
+            data.cookware = r.cookware.iter().map(|c| c.name.to_string()).collect();
+            data.required_ingredients = required_ingredients(r);
+            data.required_cookware = required_cookware(r);
+            data.references = r
+                .ingredients
+                .iter()
+                .filter(|i| is_recipe_ref(i))
+                .map(|i| i.name.to_owned())
+                .collect();
+            data.has_references = !data.references.is_empty();
+            data.steps = r
+                .sections
+                .iter()
+                .flat_map(|s| &s.content)
+                .filter(|c| matches!(c, Content::Step(_)))
+                .count();
+        }
+        data
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    modified: SystemTime,
+    size: u64,
+    data: RecipeData,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    key: String,
+    /// Relative to the collection
+    recipes: HashMap<Utf8PathBuf, CacheEntry>,
+}
+
+pub struct RecipeCache {
+    base_path: Utf8PathBuf,
+    /// `None` when not in a collection, the cache is not saved
+    file: Option<Utf8PathBuf>,
+    content: CacheFile,
+    used: HashSet<Utf8PathBuf>,
+    dirty: bool,
+}
+
+impl RecipeCache {
+    /// Loads the cache of the collection
+    ///
+    /// If it can't be read or is outdated, starts empty.
+    pub fn load(base_path: &Utf8Path, config: &Config) -> Self {
+        let cook_dir = base_path.join(COOK_DIR);
+        let file = cook_dir
+            .is_dir()
+            .then(|| cook_dir.join(CACHE_DIR).join(CACHE_FILE));
+        let key = cache_key(base_path, config);
+
+        let content = file
+            .as_ref()
+            .filter(|f| f.is_file())
+            .and_then(|f| {
+                let text = std::fs::read(f).ok()?;
+                match serde_json::from_slice::<CacheFile>(&text) {
+                    Ok(c) => Some(c),
+                    Err(err) => {
+                        tracing::warn!("Ignoring broken cache file: {err}");
+                        None
+                    }
+                }
+            })
+            .filter(|c| {
+                let valid = c.key == key;
+                if !valid {
+                    tracing::debug!("Config changed, cache discarded");
+                }
+                valid
+            })
+            .unwrap_or_else(|| CacheFile {
+                key,
+                recipes: HashMap::new(),
+            });
+
+        Self {
+            base_path: base_path.to_path_buf(),
+            file,
+            content,
+            used: HashSet::new(),
+            dirty: false,
+        }
+    }
+
+    /// Gets the data of a recipe, parsing it if it changed
+    pub fn get(
+        &mut self,
+        entry: &RecipeEntry,
+        parser: &CooklangParser,
+    ) -> Result<&RecipeData, cooklang_fs::Error> {
+        let key = self.key(entry.path());
+        let stamp = stamp(entry.path());
+
+        let fresh = match (self.content.recipes.get(&key), stamp) {
+            (Some(cached), Some((modified, size))) => {
+                cached.modified == modified && cached.size == size
+            }
+            _ => false,
+        };
+        if !fresh {
+            tracing::trace!("parsing {key}, not in cache");
+            let data = RecipeData::parse(&entry.read()?.into_text(), parser);
+            self.insert(key.clone(), stamp, data);
+        }
+        self.used.insert(key.clone());
+        Ok(&self.content.recipes[&key].data)
+    }
+
+    /// Parses in parallel the recipes that changed
+    ///
+    /// After this, [`RecipeCache::get`] of any of the entries won't parse.
+    pub fn update(&mut self, entries: &[RecipeEntry], parser: &CooklangParser) {
+        let stale = entries
+            .iter()
+            .filter_map(|entry| {
+                let key = self.key(entry.path());
+                let stamp = stamp(entry.path());
+                let fresh = self.content.recipes.get(&key).is_some_and(|cached| {
+                    stamp.is_some_and(|s| cached.modified == s.0 && cached.size == s.1)
+                });
+                (!fresh).then_some((entry, key, stamp))
+            })
+            .collect::<Vec<_>>();
+        if stale.is_empty() {
+            return;
+        }
+        tracing::debug!("parsing {} recipes not in cache", stale.len());
+        let parsed = par_map(&stale, |(entry, _, _)| {
+            let text = entry.read().ok()?.into_text();
+            Some(RecipeData::parse(&text, parser))
+        });
+        for ((_, key, stamp), data) in stale.into_iter().zip(parsed) {
+            // unreadable ones will fail again in `get`
+            let Some(data) = data else { continue };
+            self.insert(key, stamp, data);
+        }
+    }
+
+    /// Modification time of a recipe when it was last read with
+    /// [`RecipeCache::get`]
+    pub fn modified(&self, path: &Utf8Path) -> Option<SystemTime> {
+        let cached = self.content.recipes.get(&self.key(path))?;
+        // no stamp
+        (cached.size != u64::MAX).then_some(cached.modified)
+    }
+
+    fn key(&self, path: &Utf8Path) -> Utf8PathBuf {
+        path.strip_prefix(&self.base_path)
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+
+    fn insert(&mut self, key: Utf8PathBuf, stamp: Option<(SystemTime, u64)>, data: RecipeData) {
+        // without a stamp it will be parsed again next time
+        let (modified, size) = stamp.unwrap_or((SystemTime::UNIX_EPOCH, u64::MAX));
+        self.content.recipes.insert(
+            key,
+            CacheEntry {
+                modified,
+                size,
+                data,
+            },
+        );
+        self.dirty = true;
+    }
+
+    pub fn remove(&mut self, path: &Utf8Path) {
+        let key = path.strip_prefix(&self.base_path).unwrap_or(path);
+        if self.content.recipes.remove(key).is_some() {
+            self.used.remove(key);
+            self.dirty = true;
+        }
+    }
+
+    /// Writes the cache if anything changed
+    ///
+    /// Recipes not accessed since it was loaded are dropped, so call this
+    /// only after going through all the recipes.
+    pub fn save(&mut self) {
+        if self.take_changes() {
+            self.write();
+        }
+    }
+
+    /// Drops the recipes not accessed since it was loaded and returns if the
+    /// file has to be written with [`RecipeCache::write`]
+    ///
+    /// This is [`RecipeCache::save`] in two steps, so the slow part only
+    /// needs shared access.
+    pub fn take_changes(&mut self) -> bool {
+        if self.file.is_none() {
+            return false;
+        }
+        let before = self.content.recipes.len();
+        self.content.recipes.retain(|k, _| self.used.contains(k));
+        let changed = self.dirty || before != self.content.recipes.len();
+        self.dirty = false;
+        changed
+    }
+
+    /// Writes the cache file, even if nothing changed
+    pub fn write(&self) {
+        let Some(file) = &self.file else { return };
+        // atomic, another chef may be reading it
+        let res = serde_json::to_vec(&self.content)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_atomic(file, &json));
+        if let Err(err) = res {
+            tracing::warn!("Could not write cache: {err}");
+        }
+    }
+}
+
+fn stamp(path: &Utf8Path) -> Option<(SystemTime, u64)> {
+    let m = std::fs::metadata(path).ok()?;
+    Some((m.modified().ok()?, m.len()))
+}
+
+/// Changes when anything that can change the parse result changes
+///
+/// The hasher has fixed keys, unlike std's `DefaultHasher` it gives the same
+/// key with any Rust version.
+fn cache_key(base_path: &Utf8Path, config: &Config) -> String {
+    let mut hasher = SipHasher13::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    config.extensions.bits().hash(&mut hasher);
+    config.recipe_ref_check.hash(&mut hasher);
+    config.default_units.hash(&mut hasher);
+    for file in config.units(base_path) {
+        file.hash(&mut hasher);
+        std::fs::read(&file).unwrap_or_default().hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_stable() {
+        let text = r#"
+            [ui.tags]
+            mexican = { emoji = ":taco:" }
+            dessert = { emoji = ":cake:" }
+            vegan = { emoji = ":seedling:" }
+        "#;
+        let base_path = Utf8Path::new("/nonexistent");
+        let key = |text| cache_key(base_path, &toml::from_str::<Config>(text).unwrap());
+        assert_eq!(key(text), key(text));
+        assert_eq!(key(text), key(""));
+        assert_ne!(key(text), key("default_units = false"));
+    }
+}
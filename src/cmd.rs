@@ -2,11 +2,19 @@ pub mod collection;
 pub mod config;
 pub mod convert;
 pub mod edit;
+pub mod fmt;
 pub mod generate_completions;
+pub mod ingredients;
 pub mod list;
+pub mod mv;
 pub mod new;
 pub mod recipe;
+pub mod rm;
 #[cfg(feature = "serve")]
 pub mod serve;
 pub mod shopping_list;
+pub mod stats;
+pub mod suggest;
+pub mod tags;
+pub mod trash;
 pub mod units;
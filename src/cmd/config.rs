@@ -202,6 +202,8 @@ fn display_regular(ctx: &Context) -> Result<()> {
         .units(&ctx.base_path)
         .iter()
         .chain(ctx.config.aisle(&ctx.base_path).iter())
+        .chain(ctx.config.aliases(&ctx.base_path).iter())
+        .chain(ctx.config.pantry(&ctx.base_path).iter())
     {
         print!("{file} {} ", "--".dimmed());
         if file.is_file() {
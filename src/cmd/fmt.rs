@@ -0,0 +1,188 @@
+use anstream::{eprintln, print, println};
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang::{CooklangParser, Extensions, ParseOptions, ScalableRecipe};
+use cooklang_fs::{all_recipes_with, RecipeEntry};
+use cooklang_to_cooklang::trim_steps;
+use owo_colors::OwoColorize;
+
+use crate::{
+    util::{metadata_validator, par_map, unwrap_recipe, write_atomic},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct FmtArgs {
+    /// Recipes or directories to format, none for the whole collection
+    ///
+    /// A recipe can also be given by name, like in `chef recipe`.
+    #[arg(value_hint = clap::ValueHint::AnyPath)]
+    paths: Vec<Utf8PathBuf>,
+
+    /// Don't write the files, exit with an error if any needs formatting
+    #[arg(long)]
+    check: bool,
+
+    /// Print the changes as a diff instead of writing them
+    #[arg(long)]
+    diff: bool,
+}
+
+pub fn run(ctx: &Context, args: FmtArgs) -> Result<()> {
+    let entries = collect_entries(ctx, &args.paths)?;
+    let parser = ctx.parser()?;
+    let extensions = ctx.config.extensions;
+    let prepared = par_map(&entries, |entry| format_text(parser, extensions, entry));
+
+    let mut changed = 0;
+    let mut failed = 0;
+    for (entry, prepared) in entries.iter().zip(prepared) {
+        let path = entry.path();
+        let (text, formatted) = match format_entry(ctx, entry, prepared) {
+            Ok(r) => r,
+            Err(err) => {
+                failed += 1;
+                eprintln!("{} {path}: {err:#}", "Can't format".red().bold());
+                continue;
+            }
+        };
+        if text == formatted {
+            continue;
+        }
+        changed += 1;
+
+        if args.diff {
+            print_diff(path, &text, &formatted);
+        } else if args.check {
+            println!("{path}");
+        } else {
+            write_atomic(path, formatted.as_bytes())
+                .with_context(|| format!("Failed to write '{path}'"))?;
+            eprintln!("{} {path}", "Formatted".green().bold());
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} recipe(s) could not be formatted");
+    }
+    if changed > 0 && args.check {
+        eprintln!("{changed} recipe(s) need formatting");
+        std::process::exit(1);
+    }
+    if changed == 0 {
+        eprintln!("{}", "All recipes are formatted".green().bold());
+    }
+
+    Ok(())
+}
+
+fn collect_entries(ctx: &Context, paths: &[Utf8PathBuf]) -> Result<Vec<RecipeEntry>> {
+    let mut entries = Vec::new();
+    if paths.is_empty() {
+        if !ctx.is_collection {
+            bail!("`fmt` needs to run inside a collection or be given the paths to format");
+        }
+        entries.extend(all_recipes_with(
+            &ctx.base_path,
+            ctx.config.max_depth,
+            &ctx.symlinks,
+        )?);
+    }
+    for path in paths {
+        if path.is_dir() {
+            entries.extend(all_recipes_with(path, ctx.config.max_depth, &ctx.symlinks)?);
+        } else if path.extension() == Some("cook") && path.is_file() {
+            entries.push(RecipeEntry::new(path));
+        } else {
+            entries.push(ctx.recipe_index.resolve(path.as_str(), None)?);
+        }
+    }
+    entries.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+    entries.dedup_by(|a, b| a.path() == b.path());
+    Ok(entries)
+}
+
+/// A recipe read and formatted
+struct Formatted {
+    text: String,
+    formatted: Result<String>,
+    /// The formatted text parsed again
+    round_trip: Option<ScalableRecipe>,
+}
+
+/// The slow part of formatting, that doesn't need the recipe index
+///
+/// References are not checked, they don't change the parsed recipe.
+fn format_text(
+    parser: &CooklangParser,
+    extensions: Extensions,
+    entry: &RecipeEntry,
+) -> Result<Formatted> {
+    let text = entry.read()?.into_text();
+    let formatted = cooklang_to_cooklang::LosslessRecipe::parse(&text, extensions)
+        .map(|r| r.formatted())
+        .map_err(anyhow::Error::from);
+    let round_trip = formatted.as_ref().ok().and_then(|formatted| {
+        let options = ParseOptions {
+            recipe_ref_check: None,
+            metadata_validator: Some(Box::new(metadata_validator)),
+        };
+        parser.parse_with_options(formatted, options).into_output()
+    });
+    Ok(Formatted {
+        text,
+        formatted,
+        round_trip,
+    })
+}
+
+/// Returns the original text and the formatted one
+///
+/// Comments and blank lines are kept. The formatted text is parsed again and
+/// compared with the original recipe, so formatting never changes the meaning
+/// of a recipe.
+fn format_entry(
+    ctx: &Context,
+    entry: &RecipeEntry,
+    prepared: Result<Formatted>,
+) -> Result<(String, String)> {
+    let Formatted {
+        text,
+        formatted,
+        round_trip,
+    } = prepared?;
+    let parser = ctx.parser()?;
+
+    let res = parser.parse_with_options(&text, ctx.parse_options(Some(entry.path())));
+    let recipe = unwrap_recipe(res, entry.file_name(), &text, ctx)?;
+
+    let formatted = formatted?;
+    if round_trip.map(trim_steps) != Some(trim_steps(recipe)) {
+        bail!("formatting would change the recipe");
+    }
+
+    Ok((text, formatted))
+}
+
+pub(crate) fn print_diff(path: &Utf8Path, old: &str, new: &str) {
+    use similar::{ChangeTag, TextDiff};
+
+    let diff = TextDiff::from_lines(old, new);
+    println!("{}", format!("--- {path}").bold());
+    println!("{}", format!("+++ {path}").bold());
+    for hunk in diff.unified_diff().iter_hunks() {
+        println!("{}", hunk.header().cyan());
+        for change in hunk.iter_changes() {
+            let line = match change.tag() {
+                ChangeTag::Delete => format!("-{change}").red().to_string(),
+                ChangeTag::Insert => format!("+{change}").green().to_string(),
+                ChangeTag::Equal => format!(" {change}"),
+            };
+            print!("{line}");
+            if change.missing_newline() {
+                println!();
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use anstream::{eprintln, print};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+use cooklang_fs::all_recipes_with;
+use cooklang_to_cooklang::{Component, ComponentKind, LosslessRecipe};
+use owo_colors::OwoColorize;
+
+use crate::{
+    cmd::fmt::print_diff,
+    util::{par_map, write_atomic},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct IngredientsArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List all ingredient names with the recipes that use them
+    #[command(visible_alias = "ls")]
+    List {
+        /// Sort by number of recipes instead of by name
+        #[arg(long)]
+        by_count: bool,
+    },
+    /// Rename an ingredient in all recipes
+    ///
+    /// The name is matched ignoring case. Quantities, modifiers, aliases and
+    /// notes are kept.
+    Rename {
+        old: String,
+        new: String,
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+pub fn run(ctx: &Context, args: IngredientsArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`ingredients` needs to run inside a collection");
+    }
+
+    match args.command {
+        Command::List { by_count } => list(ctx, by_count),
+        Command::Rename { old, new, yes } => rename(ctx, &old, &new, yes),
+    }
+}
+
+/// Ingredients, not references to other recipes
+fn is_ingredient(c: &Component) -> bool {
+    c.kind() == ComponentKind::Ingredient && !crate::cmd::mv::is_reference(c)
+}
+
+fn list(ctx: &Context, by_count: bool) -> Result<()> {
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let extensions = ctx.config.extensions;
+    let names = par_map(&entries, |entry| -> Result<Vec<String>> {
+        let text = entry.read()?.into_text();
+        let recipe = match LosslessRecipe::parse(&text, extensions) {
+            Ok(r) => r,
+            Err(err) => {
+                tracing::warn!("Skipping '{}': {err}", entry.path());
+                return Ok(Vec::new());
+            }
+        };
+        Ok(recipe
+            .components()
+            .filter(|c| is_ingredient(c))
+            .filter_map(|c| c.name())
+            .map(str::to_string)
+            .collect())
+    });
+
+    let mut used_in = BTreeMap::<String, Vec<String>>::new();
+    for (entry, names) in entries.iter().zip(names) {
+        let rel = entry
+            .path()
+            .strip_prefix(&ctx.base_path)
+            .unwrap_or(entry.path())
+            .with_extension("")
+            .into_string();
+        for name in names? {
+            let recipes = used_in.entry(name).or_default();
+            if !recipes.contains(&rel) {
+                recipes.push(rel.clone());
+            }
+        }
+    }
+
+    let mut all = used_in.into_iter().collect::<Vec<_>>();
+    if by_count {
+        all.sort_by_key(|(_, recipes)| std::cmp::Reverse(recipes.len()));
+    } else {
+        all.sort_by_cached_key(|(name, _)| name.to_lowercase());
+    }
+    let mut table = tabular::Table::new("{:>}  {:<}  {:<}");
+    for (name, recipes) in all {
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(recipes.len())
+                .with_cell(name)
+                .with_ansi_cell(recipes.join(", ").dimmed().to_string()),
+        );
+    }
+    print!("{table}");
+    Ok(())
+}
+
+fn rename(ctx: &Context, old: &str, new: &str, yes: bool) -> Result<()> {
+    let new = new.trim();
+    if new.is_empty() {
+        bail!("The new name can't be empty");
+    }
+    if new.contains(['{', '}', '(', ')', '@', '#', '~']) {
+        bail!("Invalid ingredient name '{new}'");
+    }
+
+    let old = old.trim().to_lowercase();
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let extensions = ctx.config.extensions;
+    let edits = par_map(&entries, |entry| -> Result<Option<(String, String)>> {
+        let text = entry.read()?.into_text();
+        let mut recipe = match LosslessRecipe::parse(&text, extensions) {
+            Ok(r) => r,
+            Err(err) => {
+                tracing::warn!("Skipping '{}': {err}", entry.path());
+                return Ok(None);
+            }
+        };
+        for c in recipe.components_mut() {
+            if is_ingredient(c) && c.name().is_some_and(|n| n.to_lowercase() == old) {
+                c.set_name(new);
+            }
+        }
+        if !recipe.is_edited() {
+            return Ok(None);
+        }
+        let new_text = recipe.to_string();
+        Ok(Some((text, new_text)))
+    });
+
+    let mut updates: Vec<(Utf8PathBuf, String, String)> = Vec::new();
+    for (entry, edit) in entries.iter().zip(edits) {
+        if let Some((text, new_text)) = edit? {
+            updates.push((entry.path().to_path_buf(), text, new_text));
+        }
+    }
+
+    if updates.is_empty() {
+        eprintln!("No recipe uses '{old}'");
+        return Ok(());
+    }
+    for (path, old_text, new_text) in &updates {
+        print_diff(path, old_text, new_text);
+    }
+
+    if !yes {
+        let confirm = inquire::Confirm::new(&format!(
+            "Rename '{old}' to '{new}' in {} recipe(s)?",
+            updates.len()
+        ))
+        .with_default(false)
+        .prompt()?;
+        if !confirm {
+            eprintln!("Nothing was changed");
+            return Ok(());
+        }
+    }
+    for (path, _, new_text) in &updates {
+        write_atomic(path, new_text.as_bytes())
+            .with_context(|| format!("Failed to write '{path}'"))?;
+    }
+    eprintln!("Updated {} recipe(s)", updates.len());
+    Ok(())
+}
@@ -1,9 +1,15 @@
 use anstream::print;
 use anyhow::{bail, Result};
 use clap::{builder::ArgPredicate, Args};
-use cooklang_fs::all_recipes;
+use cooklang_fs::all_recipes_with;
 
-use crate::{util::CachedRecipeEntry, Context};
+use cooklang::Metadata;
+
+use crate::{
+    cache::{RecipeCache, RecipeData},
+    util::CachedRecipeEntry,
+    Context,
+};
 
 #[derive(Debug, Args)]
 pub struct ListArgs {
@@ -55,40 +61,61 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
         bail!("`list` needs to run inside a collection or pass `--force`");
     }
 
-    let iter = all_recipes(&ctx.base_path, ctx.config.max_depth)?.filter_map(|entry| {
+    // only parse if needed
+    let mut cache = (args.check || args.tags || !args.tag.is_empty())
+        .then(|| RecipeCache::load(&ctx.base_path, &ctx.config));
+
+    let all =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    if let Some(cache) = &mut cache {
+        cache.update(&all, ctx.parser()?);
+    }
+    let mut entries = Vec::new();
+    for entry in all {
         let entry = CachedRecipeEntry::new(entry);
-        if args.tag.is_empty() {
-            return Some(entry);
-        }
-        let m = entry.metadata(ctx, args.check); // try full parse if check to avoid parsing the recipe twice
-        let Ok(metadata) = m else {
-            tracing::warn!("Skipping '{}': could not parse metadata", entry.path());
-            return None;
+        let data = match &mut cache {
+            // unreadable ones fail again below, per recipe
+            Some(cache) => cache.get(&entry, ctx.parser()?).ok().cloned(),
+            None => None,
         };
-        if !args
-            .tag
-            .iter()
-            .all(|t| metadata.tags().is_some_and(|tags| tags.contains(t)))
-        {
-            return None;
+        if !args.tag.is_empty() {
+            let Ok(metadata) = metadata(ctx, &entry, data.as_ref()) else {
+                tracing::warn!("Skipping '{}': could not parse metadata", entry.path());
+                continue;
+            };
+            if !args
+                .tag
+                .iter()
+                .all(|t| metadata.tags().is_some_and(|tags| tags.contains(t)))
+            {
+                continue;
+            }
         }
-        Some(entry)
-    });
+        entries.push((entry, data));
+    }
+    if let Some(cache) = &mut cache {
+        cache.save();
+    }
+    let iter = entries.into_iter();
+
     if args.count {
         let mut count = 0;
         let mut with_warnings = 0;
         let mut with_errors = 0;
         let mut with_images = 0;
         let mut total_images = 0;
-        for entry in iter {
+        for (entry, data) in iter {
             count += 1;
             if args.check || args.images {
-                if args.check {
-                    let report = entry.parsed(ctx)?.report();
-                    if report.has_errors() {
+                if let Some((errors, warnings)) = args
+                    .check
+                    .then(|| check(ctx, &entry, data.as_ref()))
+                    .flatten()
+                {
+                    if errors {
                         with_errors += 1;
                     }
-                    if report.has_warnings() {
+                    if warnings {
                         with_warnings += 1;
                     }
                 }
@@ -116,9 +143,9 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
     } else {
         let mut table = tabular::Table::new("{:<}{:<}{:<}{:<}");
         let mut all = iter.collect::<Vec<_>>();
-        all.sort_unstable_by(|a, b| a.path().cmp(b.path()));
-        for entry in &all {
-            let row = list_row(ctx, &args, entry)?;
+        all.sort_unstable_by(|a, b| a.0.path().cmp(b.0.path()));
+        for (entry, data) in &all {
+            let row = list_row(ctx, &args, entry, data.as_ref())?;
             table.add_row(row);
         }
         print!("{table}");
@@ -127,7 +154,12 @@ pub fn run(ctx: &Context, args: ListArgs) -> Result<()> {
     Ok(())
 }
 
-fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result<tabular::Row> {
+fn list_row(
+    ctx: &Context,
+    args: &ListArgs,
+    entry: &CachedRecipeEntry,
+    data: Option<&RecipeData>,
+) -> Result<tabular::Row> {
     use owo_colors::OwoColorize;
 
     let mut row = tabular::Row::new();
@@ -155,7 +187,7 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
     row.add_ansi_cell(name);
 
     if args.tags {
-        if let Ok(metadata) = entry.metadata(ctx, args.check) {
+        if let Ok(metadata) = metadata(ctx, entry, data) {
             if let Some(tags) = metadata.tags() {
                 row.add_cell(format!(" [{}]", tags.join(", ")));
             } else {
@@ -169,7 +201,7 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
     }
 
     if args.check {
-        row.add_ansi_cell(format!(" [{}]", check_str(ctx, entry)));
+        row.add_ansi_cell(format!(" [{}]", check_str(ctx, entry, data)));
     } else {
         row.add_cell("");
     };
@@ -188,21 +220,46 @@ fn list_row(ctx: &Context, args: &ListArgs, entry: &CachedRecipeEntry) -> Result
     Ok(row)
 }
 
-fn check_str(ctx: &Context, entry: &CachedRecipeEntry) -> String {
+/// Metadata from the cache or parsing only the metadata if the recipe has
+/// errors
+fn metadata<'a>(
+    ctx: &Context,
+    entry: &'a CachedRecipeEntry,
+    data: Option<&'a RecipeData>,
+) -> Result<&'a Metadata> {
+    match data.and_then(|d| d.metadata.as_ref()) {
+        Some(m) => Ok(m),
+        None => entry.metadata(ctx, false),
+    }
+}
+
+/// If the recipe has errors and warnings
+///
+/// The cache doesn't know if the references are right, so the recipes with
+/// them are parsed again.
+fn check(
+    ctx: &Context,
+    entry: &CachedRecipeEntry,
+    data: Option<&RecipeData>,
+) -> Option<(bool, bool)> {
+    match data {
+        Some(d) if !(d.has_references && ctx.config.recipe_ref_check) => {
+            Some((d.errors > 0, d.warnings > 0))
+        }
+        _ => {
+            let report = entry.parsed(ctx).ok()?.report();
+            Some((report.has_errors(), report.has_warnings()))
+        }
+    }
+}
+
+fn check_str(ctx: &Context, entry: &CachedRecipeEntry, data: Option<&RecipeData>) -> String {
     use owo_colors::OwoColorize;
 
-    entry
-        .parsed(ctx)
-        .ok()
-        .map(|r| r.report())
-        .map(|report| {
-            if report.has_errors() {
-                "Error".red().bold().to_string()
-            } else if report.has_warnings() {
-                "Warn".yellow().bold().to_string()
-            } else {
-                "Ok".green().bold().to_string()
-            }
-        })
-        .unwrap_or("Could not check".red().dimmed().to_string())
+    match check(ctx, entry, data) {
+        Some((true, _)) => "Error".red().bold().to_string(),
+        Some((false, true)) => "Warn".yellow().bold().to_string(),
+        Some((false, false)) => "Ok".green().bold().to_string(),
+        None => "Could not check".red().dimmed().to_string(),
+    }
 }
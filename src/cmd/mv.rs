@@ -0,0 +1,492 @@
+use std::{collections::HashMap, fs};
+
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use clap::Args;
+use cooklang_fs::{all_recipes_with, recipe_images, RecipeEntry};
+use cooklang_to_cooklang::{ComponentKind, LosslessRecipe};
+use owo_colors::OwoColorize;
+
+use crate::{
+    util::{par_map, write_atomic},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct MvArgs {
+    /// Recipe to move
+    ///
+    /// A path or a name, like in `chef recipe`.
+    from: String,
+
+    /// New name of the recipe
+    ///
+    /// Split directories with "/". If it ends with "/" or is an existing
+    /// directory, the recipe is moved there keeping its name.
+    to: String,
+
+    /// Show what would change without touching any file
+    #[arg(long, short = 'n')]
+    dry_run: bool,
+}
+
+/// A recipe that references the moved one and has to be rewritten
+struct Update {
+    /// Where the file will be written
+    path: Utf8PathBuf,
+    text: String,
+    /// Old and new reference names
+    changes: Vec<(String, String)>,
+}
+
+pub fn run(ctx: &Context, args: MvArgs) -> Result<()> {
+    let base = ctx
+        .base_path
+        .canonicalize_utf8()
+        .context("Failed to resolve the base path")?;
+
+    let from_entry = if args.from.ends_with(".cook") && Utf8Path::new(&args.from).is_file() {
+        RecipeEntry::new(&args.from)
+    } else {
+        ctx.recipe_index.resolve(&args.from, None)?
+    };
+    let from_rel = relative_to_base(from_entry.path(), &base)?;
+    let to_rel = target_path(&ctx.base_path, &args.to, &from_rel)?;
+    if compare_key(&from_rel) == compare_key(&to_rel) && from_rel != to_rel {
+        // only the case changes, the index can't tell them apart
+        bail!("Changing only the case of a recipe name is not supported");
+    }
+    if from_rel == to_rel {
+        bail!("The recipe is already at '{to_rel}'");
+    }
+    let to_path = ctx.base_path.join(&to_rel);
+    if to_path.exists() {
+        bail!("File already exists: '{to_path}'");
+    }
+
+    let images = move_images(&from_entry, &to_path)?;
+    let updates = find_updates(ctx, &base, &from_rel, &to_rel, &to_path)?;
+
+    println!("{} {from_rel} -> {to_rel}", "Move".green().bold());
+    let shown = |p: &Utf8Path| p.strip_prefix(&ctx.base_path).unwrap_or(p).to_owned();
+    for (old, new) in &images {
+        println!("{} {} -> {}", "Move".green().bold(), shown(old), shown(new));
+    }
+    for update in &updates {
+        println!("{} {}", "Update".yellow().bold(), shown(&update.path));
+        for (old, new) in &update.changes {
+            println!("    {} -> {}", old.red(), new.green());
+        }
+    }
+
+    if args.dry_run {
+        eprintln!("{}", "Dry run, nothing was changed".yellow());
+        return Ok(());
+    }
+
+    if let Some(parent) = to_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut moves = vec![(from_entry.path().to_path_buf(), to_path.clone())];
+    moves.extend(images.iter().cloned());
+    for (i, (old, new)) in moves.iter().enumerate() {
+        if let Err(err) = fs::rename(old, new) {
+            // put back the files already moved, so the recipe is not split
+            for (old, new) in moves[..i].iter().rev() {
+                if let Err(err) = fs::rename(new, old) {
+                    tracing::error!("Failed to move '{new}' back to '{old}': {err}");
+                }
+            }
+            return Err(err)
+                .with_context(|| format!("Failed to move '{old}', nothing was changed"));
+        }
+    }
+    for (i, update) in updates.iter().enumerate() {
+        if let Err(err) = write_atomic(&update.path, update.text.as_bytes()) {
+            let pending = updates[i..]
+                .iter()
+                .map(|u| format!("\n    {}", shown(&u.path)))
+                .collect::<String>();
+            return Err(err).with_context(|| {
+                format!(
+                    "Failed to write '{}'. The files were moved, but these recipes still have the old references:{pending}",
+                    update.path
+                )
+            });
+        }
+    }
+
+    eprintln!(
+        "Moved {} file(s) and updated {} recipe(s)",
+        images.len() + 1,
+        updates.len()
+    );
+    Ok(())
+}
+
+/// Destination of the recipe relative to the base path
+fn target_path(base_path: &Utf8Path, to: &str, from_rel: &Utf8Path) -> Result<Utf8PathBuf> {
+    let mut file = Utf8Path::new(to)
+        .components()
+        .filter(|c| *c != Utf8Component::CurDir)
+        .collect::<Utf8PathBuf>();
+    if to.ends_with('/') || file.as_str().is_empty() || base_path.join(&file).is_dir() {
+        file.push(from_rel.file_name().expect("recipe without file name"));
+    }
+    if file.extension() != Some("cook") {
+        let name = format!("{}.cook", file.file_name().unwrap_or_default());
+        file.set_file_name(name);
+    }
+    let valid = file
+        .components()
+        .all(|c| matches!(c, Utf8Component::Normal(_)));
+    if !valid {
+        bail!("Invalid name: {to}");
+    }
+    Ok(file)
+}
+
+/// Step images renamed as the new recipe
+fn move_images(from: &RecipeEntry, to_path: &Utf8Path) -> Result<Vec<(Utf8PathBuf, Utf8PathBuf)>> {
+    let old_stem = from.name();
+    let new_stem = to_path.file_stem().expect("recipe without file name");
+    let dir = to_path.parent().unwrap_or(Utf8Path::new(""));
+
+    let mut moves = Vec::new();
+    for image in recipe_images(from.path()) {
+        let file_name = image.path.file_name().expect("image without file name");
+        let rest = &file_name[old_stem.len()..];
+        let new = dir.join(format!("{new_stem}{rest}"));
+        if new.exists() {
+            bail!("File already exists: '{new}'");
+        }
+        moves.push((image.path, new));
+    }
+    Ok(moves)
+}
+
+/// Recipes with references that change with the move
+///
+/// This includes the moved recipe itself, as its relative references change
+/// when it's moved to another directory.
+fn find_updates(
+    ctx: &Context,
+    base: &Utf8Path,
+    from_rel: &Utf8Path,
+    to_rel: &Utf8Path,
+    to_path: &Utf8Path,
+) -> Result<Vec<Update>> {
+    let extensions = ctx.config.extensions;
+    let mut updates = Vec::new();
+    for (entry, text, references) in read_references(ctx)? {
+        let entry_rel = relative_to_base(entry.path(), base)?;
+        let moved = entry_rel == from_rel;
+
+        let old_dir = entry_rel.parent().unwrap_or(Utf8Path::new(""));
+        let new_dir = if moved {
+            to_rel.parent().unwrap_or(Utf8Path::new(""))
+        } else {
+            old_dir
+        };
+        let mut renames = HashMap::new();
+        for name in references {
+            if renames.contains_key(&name) {
+                continue;
+            }
+            let Ok(target) = ctx.recipe_index.resolve(&name, entry.path().parent()) else {
+                continue;
+            };
+            let target_rel = relative_to_base(target.path(), base)?;
+            let target_moved = target_rel == from_rel;
+            if !moved && !target_moved {
+                continue;
+            }
+            let new_target = if target_moved { to_rel } else { &target_rel };
+            let new_name = rewrite_reference(&name, old_dir, new_dir, &target_rel, new_target);
+            if new_name != name {
+                renames.insert(name, new_name);
+            }
+        }
+        if renames.is_empty() {
+            continue;
+        }
+
+        // parsed again to edit it, only the few recipes that change
+        let Ok(mut recipe) = LosslessRecipe::parse(&text, extensions) else {
+            continue;
+        };
+        let mut changes = Vec::new();
+        for component in recipe.components_mut() {
+            let Some((name, new_name)) = component
+                .name()
+                .filter(|_| is_reference(component))
+                .and_then(|n| renames.get_key_value(n))
+            else {
+                continue;
+            };
+            changes.push((name.clone(), new_name.clone()));
+            component.set_name(new_name.clone());
+        }
+
+        updates.push(Update {
+            path: if moved {
+                to_path.to_path_buf()
+            } else {
+                entry.path().to_path_buf()
+            },
+            text: recipe.to_string(),
+            changes,
+        });
+    }
+    Ok(updates)
+}
+
+/// Recipes with references, with their text and the referenced names
+///
+/// They are read and parsed in parallel, and returned in the walk order.
+pub(crate) fn read_references(ctx: &Context) -> Result<Vec<(RecipeEntry, String, Vec<String>)>> {
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let extensions = ctx.config.extensions;
+    let parsed = par_map(&entries, |entry| -> Result<Option<(String, Vec<String>)>> {
+        let text = entry.read()?.into_text();
+        if !text.contains('@') {
+            return Ok(None);
+        }
+        let references = match LosslessRecipe::parse(&text, extensions) {
+            Ok(recipe) => recipe
+                .components()
+                .filter(|c| is_reference(c))
+                .filter_map(|c| c.name())
+                .map(str::to_string)
+                .collect(),
+            Err(err) => {
+                tracing::warn!("Can't check the references in '{}': {err}", entry.path());
+                return Ok(None);
+            }
+        };
+        Ok(Some((text, references)))
+    });
+
+    let mut found = Vec::new();
+    for (entry, parsed) in entries.into_iter().zip(parsed) {
+        if let Some((text, references)) = parsed? {
+            found.push((entry, text, references));
+        }
+    }
+    Ok(found)
+}
+
+pub(crate) fn is_reference(component: &cooklang_to_cooklang::Component) -> bool {
+    component.kind() == ComponentKind::Ingredient
+        && (component.modifiers().contains('@')
+            || component
+                .name()
+                .is_some_and(|n| n.starts_with("./") || n.starts_with("../")))
+}
+
+/// New name of a reference so it points to `new_target` from `new_dir`
+///
+/// The style of the reference is kept: plain names stay plain names, paths
+/// from the base (starting with `/`) stay like that and the rest become
+/// relative paths.
+fn rewrite_reference(
+    name: &str,
+    old_dir: &Utf8Path,
+    new_dir: &Utf8Path,
+    old_target: &Utf8Path,
+    new_target: &Utf8Path,
+) -> String {
+    let keep_ext = name.to_lowercase().ends_with(".cook");
+    let strip = |p: Utf8PathBuf| {
+        if keep_ext {
+            p
+        } else {
+            p.with_extension("")
+        }
+    };
+
+    if !name.contains('/') {
+        if old_target == new_target {
+            return name.to_string();
+        }
+        return strip(Utf8PathBuf::from(
+            new_target.file_name().expect("recipe without file name"),
+        ))
+        .into_string();
+    }
+
+    if name.starts_with('/') {
+        if old_target == new_target {
+            return name.to_string();
+        }
+        return format!("/{}", strip(new_target.to_path_buf()));
+    }
+
+    let relative = name.starts_with("./")
+        || name.starts_with("../")
+        || compare_key(&normalize(&old_dir.join(name))) == compare_key(old_target);
+    if !relative {
+        // partial path looked up in the index, keep the same number of components
+        if old_target == new_target {
+            return name.to_string();
+        }
+        let count = Utf8Path::new(name).components().count();
+        let components = new_target.components().collect::<Vec<_>>();
+        let start = components.len().saturating_sub(count);
+        let path = components[start..].iter().collect::<Utf8PathBuf>();
+        return strip(path).into_string();
+    }
+
+    let path = strip(relative_path(new_dir, new_target));
+    if path.starts_with("..") {
+        path.into_string()
+    } else {
+        format!("./{path}")
+    }
+}
+
+pub(crate) fn relative_to_base(path: &Utf8Path, base: &Utf8Path) -> Result<Utf8PathBuf> {
+    let path = path
+        .canonicalize_utf8()
+        .with_context(|| format!("Failed to resolve '{path}'"))?;
+    match path.strip_prefix(base) {
+        Ok(rel) => Ok(rel.to_path_buf()),
+        Err(_) => bail!("'{path}' is outside the collection"),
+    }
+}
+
+/// Path to `to` from the directory `from`, both relative to the same base
+fn relative_path(from: &Utf8Path, to: &Utf8Path) -> Utf8PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = Utf8PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for c in &to[common..] {
+        path.push(c);
+    }
+    path
+}
+
+fn normalize(path: &Utf8Path) -> Utf8PathBuf {
+    let mut ret = Utf8PathBuf::new();
+    for c in path.components() {
+        match c {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                ret.pop();
+            }
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// How the index compares paths: ignoring case and extension
+fn compare_key(path: &Utf8Path) -> String {
+    path.with_extension("").as_str().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(name: &str, old_dir: &str, new_dir: &str, old: &str, new: &str) -> String {
+        rewrite_reference(
+            name,
+            Utf8Path::new(old_dir),
+            Utf8Path::new(new_dir),
+            Utf8Path::new(old),
+            Utf8Path::new(new),
+        )
+    }
+
+    #[test]
+    fn relative_paths() {
+        let rel = |from, to| relative_path(Utf8Path::new(from), Utf8Path::new(to));
+        assert_eq!(rel("", "a/b.cook"), "a/b.cook");
+        assert_eq!(rel("a", "a/b.cook"), "b.cook");
+        assert_eq!(rel("a/c", "a/b.cook"), "../b.cook");
+        assert_eq!(rel("c", "a/b.cook"), "../a/b.cook");
+        assert_eq!(rel("c/d", "b.cook"), "../../b.cook");
+    }
+
+    #[test]
+    fn target_paths() {
+        let base = Utf8Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target = |to| target_path(base, to, Utf8Path::new("a/Soup.cook")).unwrap();
+        assert_eq!(target("Stew"), "Stew.cook");
+        assert_eq!(target("./Stew.cook"), "Stew.cook");
+        assert_eq!(target("b/Stew"), "b/Stew.cook");
+        assert_eq!(target("b/"), "b/Soup.cook");
+        // existing folder
+        assert_eq!(target("src"), "src/Soup.cook");
+        assert!(target_path(base, "../Stew", Utf8Path::new("Soup.cook")).is_err());
+        assert!(target_path(base, "/Stew", Utf8Path::new("Soup.cook")).is_err());
+    }
+
+    #[test]
+    fn plain_references() {
+        assert_eq!(rewrite("soup", "", "", "Soup.cook", "Stew.cook"), "Stew");
+        assert_eq!(
+            rewrite("Soup.cook", "", "", "Soup.cook", "Stew.cook"),
+            "Stew.cook"
+        );
+        // the file name didn't change
+        assert_eq!(rewrite("soup", "", "", "Soup.cook", "b/Soup.cook"), "Soup");
+        assert_eq!(rewrite("soup", "", "", "Soup.cook", "Soup.cook"), "soup");
+    }
+
+    #[test]
+    fn relative_references() {
+        // the referencing recipe moves
+        assert_eq!(
+            rewrite("./Soup", "", "b", "Soup.cook", "Soup.cook"),
+            "../Soup"
+        );
+        assert_eq!(
+            rewrite("../Soup", "a", "", "Soup.cook", "Soup.cook"),
+            "./Soup"
+        );
+        // the referenced recipe moves
+        assert_eq!(
+            rewrite("./Soup.cook", "a", "a", "a/Soup.cook", "b/Stew.cook"),
+            "../b/Stew.cook"
+        );
+        assert_eq!(
+            rewrite("../a/Soup", "c", "c", "a/Soup.cook", "c/d/Soup.cook"),
+            "./d/Soup"
+        );
+        // both move
+        assert_eq!(
+            rewrite("./x/Soup", "a", "b", "a/x/Soup.cook", "c/Soup.cook"),
+            "../c/Soup"
+        );
+    }
+
+    #[test]
+    fn path_references() {
+        assert_eq!(
+            rewrite("/a/Soup", "c", "c", "a/Soup.cook", "b/Stew.cook"),
+            "/b/Stew"
+        );
+        assert_eq!(
+            rewrite("/a/Soup.cook", "c", "c", "a/Soup.cook", "b/Soup.cook"),
+            "/b/Soup.cook"
+        );
+        // relative without ./, written from the recipe dir
+        assert_eq!(
+            rewrite("x/Soup", "a", "a", "a/x/Soup.cook", "b/Soup.cook"),
+            "../b/Soup"
+        );
+        // partial path, keeps the number of components
+        assert_eq!(
+            rewrite("x/Soup", "c", "c", "a/x/Soup.cook", "a/y/Soup.cook"),
+            "y/Soup"
+        );
+    }
+}
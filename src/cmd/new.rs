@@ -1,10 +1,13 @@
-use std::fs;
+use std::{fs, io::IsTerminal, time::SystemTime};
 
 use anyhow::{bail, Context as _, Result};
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::Args;
+use cooklang_to_cooklang::LosslessRecipe;
 
-use crate::Context;
+use crate::{config::Config, util::is_valid_tag, Context, COOK_DIR};
+
+const TEMPLATES_DIR: &str = "templates";
 
 #[derive(Debug, Args)]
 pub struct NewArgs {
@@ -13,31 +16,43 @@ pub struct NewArgs {
     /// Split directories with "/"
     name: String,
 
+    /// Start from a template in `.cooklang/templates`
+    ///
+    /// Placeholders `{{name}}`, `{{date}}`, `{{author}}` and `{{tags}}` are
+    /// replaced.
+    #[arg(long, short)]
+    template: Option<String>,
+
+    /// Ask for the servings, tags, description and time
+    #[arg(long, short, conflicts_with = "template")]
+    wizard: bool,
+
     /// Skip opening the editor
     #[arg(long, short = 'E')]
     no_edit: bool,
 }
 
 pub fn run(args: NewArgs, ctx: &Context) -> Result<()> {
-    let file = Utf8Path::new(&args.name).with_extension("cook");
-    let valid = !file.is_absolute()
-        && file
-            .components()
-            .all(|c| matches!(c, camino::Utf8Component::Normal(_)));
-    if !valid {
-        bail!("Invalid name: {}", args.name);
-    }
+    let path = recipe_path(&ctx.base_path, &args.name)?;
 
-    let path = ctx.base_path.join(file);
-
-    if path.is_file() {
-        bail!("File already exists: {}", path);
-    }
+    let name = path.file_stem().unwrap_or(&args.name);
+    let author = ctx.chef_config.author.as_deref();
+    let content = if let Some(template) = &args.template {
+        from_template(&ctx.base_path, &ctx.config, author, template, name)?
+    } else if args.wizard {
+        if !std::io::stdin().is_terminal() {
+            bail!("The wizard needs an interactive terminal");
+        }
+        wizard(ctx)?
+    } else {
+        String::new()
+    };
+    let content = with_default_metadata(&ctx.config, author, &content)?;
 
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::File::create(&path)?;
+    fs::write(&path, content)?;
 
     if !args.no_edit {
         let editor = ctx
@@ -59,3 +74,220 @@ pub fn run(args: NewArgs, ctx: &Context) -> Result<()> {
 
     Ok(())
 }
+
+/// Path of a new recipe in the collection
+///
+/// Dirs in `name` are split with "/". Fails if the name is not valid or the
+/// recipe already exists.
+pub fn recipe_path(base_path: &Utf8Path, name: &str) -> Result<Utf8PathBuf> {
+    let file = Utf8Path::new(name).with_extension("cook");
+    let valid = !file.is_absolute()
+        && file
+            .components()
+            .all(|c| matches!(c, camino::Utf8Component::Normal(_)));
+    if !valid {
+        bail!("Invalid name: {}", name);
+    }
+
+    let path = base_path.join(file);
+
+    if path.is_file() {
+        bail!("File already exists: {}", path);
+    }
+    Ok(path)
+}
+
+/// Text of a new recipe, from a template or empty, with the default metadata
+pub fn initial_text(
+    base_path: &Utf8Path,
+    config: &Config,
+    author: Option<&str>,
+    template: Option<&str>,
+    name: &str,
+) -> Result<String> {
+    let content = match template {
+        Some(template) => from_template(base_path, config, author, template, name)?,
+        None => String::new(),
+    };
+    with_default_metadata(config, author, &content)
+}
+
+fn templates_dir(base_path: &Utf8Path) -> Utf8PathBuf {
+    base_path.join(COOK_DIR).join(TEMPLATES_DIR)
+}
+
+/// Names of the templates in `.cooklang/templates`, sorted
+pub fn templates(base_path: &Utf8Path) -> Vec<String> {
+    templates_dir(base_path)
+        .read_dir_utf8()
+        .map(|entries| {
+            let mut names = entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension() == Some("cook"))
+                .filter_map(|e| e.path().file_stem().map(str::to_string))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        })
+        .unwrap_or_default()
+}
+
+/// Fails if `template` is not one of the [`templates`]
+///
+/// Only plain names are valid, so a template can't be read from outside the
+/// templates dir.
+pub fn check_template(base_path: &Utf8Path, template: &str) -> Result<()> {
+    if template.contains(['/', '\\']) || template.contains("..") {
+        bail!("Invalid template name: {template}");
+    }
+    let available = templates(base_path);
+    if available.iter().any(|t| t == template) {
+        return Ok(());
+    }
+    if available.is_empty() {
+        bail!(
+            "Template '{template}' not found. Add templates to '{}'",
+            templates_dir(base_path)
+        );
+    }
+    bail!(
+        "Template '{template}' not found. Available: {}",
+        available.join(", ")
+    );
+}
+
+fn from_template(
+    base_path: &Utf8Path,
+    config: &Config,
+    author: Option<&str>,
+    template: &str,
+    name: &str,
+) -> Result<String> {
+    check_template(base_path, template)?;
+    let path = templates_dir(base_path)
+        .join(template)
+        .with_extension("cook");
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read template '{path}'"))?;
+
+    let author = author.unwrap_or_default();
+    let tags = config.default_tags.join(", ");
+    let date = today();
+    let values = [
+        ("name", name),
+        ("date", date.as_str()),
+        ("author", author),
+        ("tags", tags.as_str()),
+    ];
+    let mut text = text;
+    for (key, value) in values {
+        text = text
+            .replace(&format!("{{{{{key}}}}}"), value)
+            .replace(&format!("{{{{ {key} }}}}"), value);
+    }
+    Ok(text)
+}
+
+fn wizard(ctx: &Context) -> Result<String> {
+    use inquire::{validator::Validation, Text};
+
+    let servings = Text::new("Servings:")
+        .with_help_message("Separate different amounts with '|'. Leave empty to skip")
+        .with_validator(|s: &str| {
+            let valid = s.is_empty() || s.split('|').all(|n| n.trim().parse::<u32>().is_ok());
+            Ok(if valid {
+                Validation::Valid
+            } else {
+                Validation::Invalid("Use numbers like '2' or '2|4'".into())
+            })
+        })
+        .prompt_skippable()?
+        .unwrap_or_default();
+    let tags = Text::new("Tags:")
+        .with_initial_value(&ctx.config.default_tags.join(", "))
+        .with_help_message("Comma separated")
+        .with_validator(|s: &str| {
+            match s
+                .split(',')
+                .map(str::trim)
+                .find(|t| !t.is_empty() && !is_valid_tag(t))
+            {
+                Some(t) => Ok(Validation::Invalid(
+                    format!("Invalid tag '{t}', use lower case words separated by '-'").into(),
+                )),
+                None => Ok(Validation::Valid),
+            }
+        })
+        .prompt_skippable()?
+        .unwrap_or_default();
+    let description = Text::new("Description:")
+        .prompt_skippable()?
+        .unwrap_or_default();
+    let time = Text::new("Time:")
+        .with_help_message("Like '1 h 30 min'")
+        .prompt_skippable()?
+        .unwrap_or_default();
+
+    let tags = tags
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut text = String::new();
+    for (key, value) in [
+        ("servings", servings.trim()),
+        ("tags", &tags),
+        ("description", description.trim()),
+        ("time", time.trim()),
+    ] {
+        if !value.is_empty() {
+            text += &format!(">> {key}: {value}\n");
+        }
+    }
+    Ok(text)
+}
+
+/// Adds the author and default tags if the recipe doesn't have them
+///
+/// Empty metadata, like placeholders without a value, is removed.
+fn with_default_metadata(config: &Config, author: Option<&str>, content: &str) -> Result<String> {
+    let mut recipe = LosslessRecipe::parse(content, config.extensions)?;
+    let empty = recipe
+        .metadata()
+        .filter(|(_, value)| value.is_empty())
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    for key in empty {
+        recipe.remove_metadata(&key);
+    }
+    if let Some(author) = author {
+        if recipe.get_metadata("author").is_none() {
+            recipe.set_metadata("author", author);
+        }
+    }
+    if !config.default_tags.is_empty() && recipe.get_metadata("tags").is_none() {
+        recipe.set_metadata("tags", config.default_tags.join(", "));
+    }
+    Ok(recipe.to_string())
+}
+
+/// Current date as `YYYY-MM-DD` in UTC
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default() as i64;
+
+    // civil from days, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
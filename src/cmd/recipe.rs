@@ -49,6 +49,11 @@ pub struct ReadArgs {
     /// Check the recipe for errors, warnings and images
     #[arg(long, conflicts_with_all = ["ScalingArgs", "DebugArgs"])]
     check: bool,
+
+    /// Render again when the recipe or a recipe it references changes
+    #[cfg(feature = "serve")]
+    #[arg(long, short, requires = "recipe", conflicts_with_all = ["check", "DebugArgs", "output"])]
+    watch: bool,
 }
 
 #[derive(Debug, Args)]
@@ -101,14 +106,24 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
         return just_check(ctx, args);
     }
 
+    #[cfg(feature = "serve")]
+    if args.watch {
+        return watch(ctx, args);
+    }
+
     let input = args.read(&ctx.recipe_index)?;
+    render(ctx, &args, &input)?;
+    Ok(())
+}
 
+/// Parses, scales and writes the recipe, returns it to know the references
+fn render(ctx: &Context, args: &ReadArgs, input: &Input) -> Result<cooklang::ScalableRecipe> {
     let recipe = input.parse(ctx)?;
 
     let mut scaled_recipe = if let Some(scale) = args.values.scale {
-        recipe.scale(scale, ctx.parser()?.converter())
+        recipe.clone().scale(scale, ctx.parser()?.converter())
     } else {
-        recipe.default_scale()
+        recipe.clone().default_scale()
     };
 
     if let Some(system) = args.values.convert {
@@ -175,7 +190,68 @@ pub fn run(ctx: &Context, args: ReadArgs) -> Result<()> {
         Ok(())
     })?;
 
-    Ok(())
+    Ok(recipe)
+}
+
+#[cfg(feature = "serve")]
+#[tokio::main(flavor = "current_thread")]
+async fn watch(ctx: &Context, args: ReadArgs) -> Result<()> {
+    use std::collections::HashSet;
+
+    use crate::{cmd::serve::async_index::Update, util::is_recipe_ref};
+
+    let input = args.read(&ctx.recipe_index)?;
+    let path = input
+        .path()
+        .expect("watching a recipe without path")
+        .canonicalize_utf8()?;
+
+    // the watcher gives the paths prefixed with the watched dir, so watch
+    // canonical paths to compare them
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    let base_path = ctx.base_path.canonicalize_utf8()?;
+    crate::cmd::serve::async_index::watch_changes_task(tx.clone(), &base_path);
+    if !path.starts_with(&base_path) {
+        let dir = path.parent().expect("recipe file without parent");
+        crate::cmd::serve::async_index::watch_changes_task(tx, dir);
+    }
+
+    let mut watched = HashSet::from([path.clone()]);
+    loop {
+        print!("\x1b[2J\x1b[H");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        match render(ctx, &args, &input) {
+            Ok(recipe) => {
+                watched.retain(|p| p == &path);
+                let parent = input.path().and_then(|p| p.parent());
+                for igr in recipe.ingredients.iter().filter(|i| is_recipe_ref(i)) {
+                    if let Ok(entry) = ctx.recipe_index.resolve(&igr.name, parent) {
+                        if let Ok(p) = entry.path().canonicalize_utf8() {
+                            watched.insert(p);
+                        }
+                    }
+                }
+            }
+            Err(err) => eprintln!("{} {err:#}", "Error:".red().bold()),
+        }
+        eprintln!("{}", "Watching for changes. Press Ctrl+C to exit.".dimmed());
+
+        // wait for a relevant change
+        loop {
+            let Some(update) = rx.recv().await else {
+                return Ok(());
+            };
+            let changed = match &update {
+                Update::Modified { path } | Update::Added { path } | Update::Deleted { path } => {
+                    watched.contains(path)
+                }
+                Update::Renamed { from, to } => watched.contains(from) || watched.contains(to),
+            };
+            if changed {
+                break;
+            }
+        }
+    }
 }
 
 impl ReadArgs {
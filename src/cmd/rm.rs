@@ -0,0 +1,104 @@
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use clap::Args;
+use cooklang_fs::{recipe_images, RecipeEntry};
+use owo_colors::OwoColorize;
+
+use crate::{
+    cmd::{
+        mv::{read_references, relative_to_base},
+        trash,
+    },
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct RmArgs {
+    /// Recipe to remove
+    ///
+    /// A path or a name, like in `chef recipe`.
+    recipe: String,
+
+    /// Don't ask for confirmation
+    #[arg(long, short)]
+    yes: bool,
+}
+
+pub fn run(ctx: &Context, args: RmArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`rm` needs to run inside a collection to keep the removed recipes");
+    }
+    let base = ctx
+        .base_path
+        .canonicalize_utf8()
+        .context("Failed to resolve the base path")?;
+
+    let entry = if args.recipe.ends_with(".cook") && Utf8Path::new(&args.recipe).is_file() {
+        RecipeEntry::new(&args.recipe)
+    } else {
+        ctx.recipe_index.resolve(&args.recipe, None)?
+    };
+    let rel = relative_to_base(entry.path(), &base)?;
+
+    let mut files = vec![entry.path().to_path_buf()];
+    files.extend(recipe_images(entry.path()).into_iter().map(|i| i.path));
+    let referenced_from = referenced_from(ctx, &base, &rel)?;
+
+    println!("{}", "Files to remove:".bold());
+    for file in &files {
+        println!("    {}", file.strip_prefix(&ctx.base_path).unwrap_or(file));
+    }
+    if !referenced_from.is_empty() {
+        println!("{}", "These references will break:".yellow().bold());
+        for (path, names) in &referenced_from {
+            println!("    {path}: {}", names.join(", "));
+        }
+    }
+
+    if !args.yes {
+        let confirm = inquire::Confirm::new(&format!("Remove '{rel}'?"))
+            .with_default(false)
+            .with_help_message("It can be restored with `chef trash restore`")
+            .prompt()?;
+        if !confirm {
+            eprintln!("Nothing was removed");
+            return Ok(());
+        }
+    }
+
+    let id = trash::put(ctx, &base, &files)?;
+    eprintln!(
+        "{} {rel}, restore it with `chef trash restore {id}`",
+        "Removed".red().bold()
+    );
+    Ok(())
+}
+
+/// Recipes that reference the one at `rel` and the names they use
+fn referenced_from(
+    ctx: &Context,
+    base: &Utf8Path,
+    rel: &Utf8Path,
+) -> Result<Vec<(String, Vec<String>)>> {
+    let mut found = Vec::new();
+    for (entry, _, references) in read_references(ctx)? {
+        let entry_rel = relative_to_base(entry.path(), base)?;
+        if entry_rel == rel {
+            continue;
+        }
+        let mut names = Vec::new();
+        for name in references {
+            let Ok(target) = ctx.recipe_index.resolve(&name, entry.path().parent()) else {
+                continue;
+            };
+            if relative_to_base(target.path(), base)? == rel && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if !names.is_empty() {
+            found.push((entry_rel.into_string(), names));
+        }
+    }
+    Ok(found)
+}
@@ -0,0 +1,392 @@
+//! JSON API in `/api/v1`
+//!
+//! Errors are JSON too, `{ "error": "message" }`, with the matching status
+//! code.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::ingredient_list::IngredientList;
+use serde::{Deserialize, Serialize};
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::shopping_list,
+    util::{is_valid_tag, meta_name},
+};
+
+use super::{
+    async_index::{RecipeData, SortBy},
+    handlers::{check_path, clean_path, image_urls, Folder, SearchQuery, Searcher},
+    shopping_list::{add_to_list, parse_aisle, read_aisle, AddError, ListRecipe},
+    S,
+};
+
+pub fn router() -> Router<S> {
+    Router::new()
+        .route("/recipes", get(recipes))
+        .route("/recipes/*path", get(recipe))
+        .route("/folders", get(folders))
+        .route("/folders/*path", get(folders))
+        .route("/tags", get(tags))
+        .route("/ingredients", get(ingredients))
+        .route("/shopping-list", post(shopping_list))
+        .fallback(|| async { ApiError::new(StatusCode::NOT_FOUND, "Unknown endpoint") })
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    /// Parser report, for recipes with errors
+    report: Option<String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            report: None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body {
+            error: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            report: Option<String>,
+        }
+        let body = Body {
+            error: self.message,
+            report: self.report,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(value: QueryRejection) -> Self {
+        Self::new(value.status(), value.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(value: JsonRejection) -> Self {
+        Self::new(value.status(), value.body_text())
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// Path of a recipe in the API, relative and without extension
+fn api_path(path: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
+    clean_path(path, base_path).with_extension("")
+}
+
+fn checked_path(state: &S, path: &str) -> Result<(), ApiError> {
+    check_path(state, path).map_err(|status| ApiError::new(status, "Invalid path"))
+}
+
+#[derive(Serialize)]
+struct RecipeSummary {
+    path: Utf8PathBuf,
+    name: String,
+    /// The recipe has errors, only `path` and `name` are valid
+    error: bool,
+    tags: Vec<String>,
+    emoji: Option<String>,
+    description: Option<String>,
+    /// Url of the main image, a thumbnail if it's in the collection
+    image: Option<String>,
+}
+
+fn recipe_summary(entry: cooklang_fs::RecipeEntry, state: &S, data: &RecipeData) -> RecipeSummary {
+    let path = api_path(entry.path(), &state.base_path);
+    let mut summary = RecipeSummary {
+        name: entry.name().to_string(),
+        path,
+        error: true,
+        tags: Vec::new(),
+        emoji: None,
+        description: None,
+        image: None,
+    };
+    if let Some(m) = &data.metadata {
+        summary.error = false;
+        if let Some(name) = meta_name(m) {
+            summary.name = name.to_string();
+        }
+        summary.tags = m.tags().unwrap_or(&[]).to_vec();
+        summary.emoji = m.emoji().map(str::to_string);
+        summary.description = m.description().map(str::to_string);
+        summary.image = m.map.get("image").cloned();
+    }
+    if summary.image.is_none() {
+        summary.image = entry
+            .images()
+            .iter()
+            .find(|i| i.indexes.is_none())
+            .map(|i| image_urls(&i.path, &state.base_path).0);
+    }
+    summary
+}
+
+#[derive(Deserialize)]
+struct RecipesQuery {
+    q: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+    #[serde(default)]
+    sort: SortBy,
+}
+
+/// Recipes matching a search, all of them without `q`
+///
+/// The number of matches, without `offset` and `limit`, is in the
+/// `X-Total-Count` header.
+async fn recipes(
+    State(state): State<S>,
+    query: Result<Query<RecipesQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    let srch = Searcher::from(SearchQuery {
+        q: query.q.filter(|q| !q.trim().is_empty()),
+    });
+    let (recipes, total) = state
+        .recipe_index
+        .search(
+            |entry, data| data.is_some_and(|d| srch.matches_recipe(entry, d, &state)),
+            |entry, data| recipe_summary(entry, &state, data.expect("filtered without data")),
+            query.sort,
+            query.offset,
+            query.limit.unwrap_or(usize::MAX),
+        )
+        .await;
+    Ok(([("X-Total-Count", total.to_string())], Json(recipes)).into_response())
+}
+
+#[derive(Deserialize)]
+struct RecipeQuery {
+    scale: Option<u32>,
+    /// `metric`, `imperial` or `default`
+    units: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonRecipe {
+    name: String,
+    path: Utf8PathBuf,
+    /// Non fatal parser warnings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warnings: Option<String>,
+    #[serde(flatten)]
+    recipe: cooklang::ScaledRecipe,
+}
+
+/// A recipe, like `chef recipe -f json`
+async fn recipe(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    query: Result<Query<RecipeQuery>, QueryRejection>,
+) -> ApiResult<JsonRecipe> {
+    let Query(query) = query?;
+    let units: Option<cooklang::convert::System> = match query.units.as_deref() {
+        None | Some("default") => None,
+        Some(sys) => Some(sys.parse().map_err(|_| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown unit system '{sys}'"),
+            )
+        })?),
+    };
+    checked_path(&state, &path)?;
+    let entry = state
+        .recipe_index
+        .get(&path)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e.to_string()))?;
+    let content = tokio::fs::read_to_string(entry.path())
+        .await
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e.to_string()))?;
+
+    let res = block_in_place(|| {
+        state
+            .parser
+            .parse_with_options(&content, state.parse_options(Some(entry.path())))
+    });
+    let report_text = |report: &cooklang::error::SourceReport| {
+        let mut buf = Vec::new();
+        report
+            .write(entry.file_name(), &content, false, &mut buf)
+            .ok()?;
+        // the report has some escape codes even without color
+        let text = String::from_utf8_lossy(&buf);
+        Some(anstream::adapter::strip_str(&text).to_string())
+    };
+    let (scalable, warnings) = match res.into_result() {
+        Ok(ok) => ok,
+        Err(report) => {
+            return Err(ApiError {
+                report: report_text(&report),
+                ..ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "The recipe has errors")
+            })
+        }
+    };
+
+    let converter = state.parser.converter();
+    let mut scaled = match query.scale {
+        Some(servings) => scalable.scale(servings, converter),
+        None => scalable.default_scale(),
+    };
+    if let Some(system) = units {
+        let _ = scaled.convert(system, converter);
+    }
+
+    Ok(Json(JsonRecipe {
+        name: meta_name(&scaled.metadata)
+            .unwrap_or(entry.name())
+            .to_string(),
+        path: api_path(entry.path(), &state.base_path),
+        warnings: (!warnings.is_empty())
+            .then(|| report_text(&warnings))
+            .flatten(),
+        recipe: scaled,
+    }))
+}
+
+/// The folder tree from the collection or a folder in it
+async fn folders(State(state): State<S>, path: Option<Path<String>>) -> ApiResult<Folder> {
+    let mut dir = state.base_path.clone();
+    if let Some(Path(p)) = &path {
+        checked_path(&state, p)?;
+        dir = dir.join(p);
+    }
+    let folder = block_in_place(|| Folder::walk(&state, &dir))
+        .map_err(|_| ApiError::new(StatusCode::NOT_FOUND, "Folder not found"))?;
+    Ok(Json(folder))
+}
+
+#[derive(Serialize)]
+struct Count {
+    name: String,
+    count: usize,
+}
+
+fn sorted_counts(counts: BTreeMap<String, usize>) -> Vec<Count> {
+    let mut counts = counts
+        .into_iter()
+        .map(|(name, count)| Count { name, count })
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts
+}
+
+/// Tags used in the collection with the number of recipes
+async fn tags(State(state): State<S>) -> ApiResult<Vec<Count>> {
+    let (all, _) = state
+        .recipe_index
+        .search(
+            |_, _| true,
+            |_, data| {
+                data.and_then(|d| d.metadata.as_ref())
+                    .and_then(|m| m.tags())
+                    .map(|t| t.to_vec())
+                    .unwrap_or_default()
+            },
+            SortBy::Path,
+            0,
+            usize::MAX,
+        )
+        .await;
+    let mut counts = BTreeMap::new();
+    for tag in all.into_iter().flatten().filter(|t| is_valid_tag(t)) {
+        *counts.entry(tag).or_default() += 1;
+    }
+    Ok(Json(sorted_counts(counts)))
+}
+
+/// Ingredients used in the collection with the number of recipes
+///
+/// Synonyms from the aliases file are counted under the same name.
+async fn ingredients(State(state): State<S>) -> ApiResult<Vec<Count>> {
+    let (all, _) = state
+        .recipe_index
+        .search(
+            |_, _| true,
+            |_, data| {
+                let mut names = data
+                    .map(|d| {
+                        d.ingredients
+                            .iter()
+                            .filter(|i| !d.references.contains(i))
+                            .map(|i| state.aliases.canonical(i).to_lowercase())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                names.sort_unstable();
+                names.dedup();
+                names
+            },
+            SortBy::Path,
+            0,
+            usize::MAX,
+        )
+        .await;
+    let mut counts = BTreeMap::new();
+    for name in all.into_iter().flatten() {
+        *counts.entry(name).or_default() += 1;
+    }
+    Ok(Json(sorted_counts(counts)))
+}
+
+#[derive(Deserialize)]
+struct ShoppingListRequest {
+    recipes: Vec<ListRecipe>,
+    /// Don't group by aisle category
+    #[serde(default)]
+    plain: bool,
+}
+
+/// Merged ingredients of several recipes, by aisle category
+async fn shopping_list(
+    State(state): State<S>,
+    body: Result<Json<ShoppingListRequest>, JsonRejection>,
+) -> ApiResult<serde_json::Value> {
+    let Json(req) = body?;
+
+    let mut list = IngredientList::new();
+    for item in &req.recipes {
+        add_to_list(&state, &mut list, item)
+            .await
+            .map_err(|err| match err {
+                AddError::InvalidPath(status) => ApiError::new(status, "Invalid path"),
+                AddError::NotFound(msg) => ApiError::new(StatusCode::NOT_FOUND, msg),
+                AddError::Invalid => ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("'{}' has errors", item.path),
+                ),
+            })?;
+    }
+
+    let internal =
+        |e: anyhow::Error| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}"));
+    let aisle_text = read_aisle(&state).map_err(internal)?;
+    let aisle = parse_aisle(&state, &aisle_text).map_err(internal)?;
+    let plain = req.plain || aisle.categories.is_empty();
+
+    Ok(Json(shopping_list::build_json_value(list, &aisle, plain)))
+}
@@ -1,4 +1,5 @@
 use std::{
+    cmp::Reverse,
     collections::BTreeMap,
     path::{Path, PathBuf},
     sync::Arc,
@@ -7,59 +8,47 @@ use std::{
 
 use anyhow::Result;
 use camino::{Utf8Path, Utf8PathBuf};
-use cooklang::{CooklangParser, Metadata};
+use cooklang::CooklangParser;
 use cooklang_fs::{FsIndex, RecipeEntry};
 use notify::{RecommendedWatcher, Watcher};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, mpsc, RwLock};
 
+pub use crate::cache::RecipeData;
+use crate::{cache::RecipeCache, util::meta_name};
+
 pub struct AsyncFsIndex {
     indexes: Arc<RwLock<Indexes>>,
 }
 
-pub struct RecipeData {
-    pub metadata: Option<Metadata>,
-    pub ingredients: Vec<String>,
-    pub cookware: Vec<String>,
-}
-
 struct Indexes {
     parser: Arc<CooklangParser>,
     fs: FsIndex,
     srch: BTreeMap<Utf8PathBuf, RecipeData>,
+    cache: RecipeCache,
 }
 
 impl Indexes {
-    fn new(fs: FsIndex, parser: Arc<CooklangParser>) -> Self {
+    fn new(fs: FsIndex, parser: Arc<CooklangParser>, mut cache: RecipeCache) -> Self {
         let mut srch = BTreeMap::new();
-        let insert_search_entry = |index: &mut BTreeMap<_, _>, entry: RecipeEntry| {
-            let recipe = entry.read().expect("can't read recipe").parse(&parser);
-            let mut ingredients = Vec::new();
-            let mut cookware = Vec::new();
-            let mut metadata = None;
-            if let Some(r) = recipe.valid_output() {
-                metadata = Some(r.metadata.to_owned());
-                for ingredient in &r.ingredients {
-                    ingredients.push(ingredient.name.to_owned());
-                }
-                for tool in &r.cookware {
-                    cookware.push(tool.name.to_string());
-                }
-            }
-            index.insert(
-                entry.path().to_owned(),
-                RecipeData {
-                    metadata,
-                    ingredients,
-                    cookware,
-                },
-            );
-        };
-        for entry in fs.get_all() {
-            insert_search_entry(&mut srch, entry);
+        let entries = fs.get_all().collect::<Vec<_>>();
+        cache.update(&entries, &parser);
+        for entry in entries {
+            let mut data = cache
+                .get(&entry, &parser)
+                .expect("can't read recipe")
+                .clone();
+            data.modified = cache.modified(entry.path());
+            srch.insert(entry.path().to_owned(), data);
         }
+        cache.save();
 
-        Self { fs, srch, parser }
+        Self {
+            fs,
+            srch,
+            parser,
+            cache,
+        }
     }
 
     fn revalidate(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
@@ -69,31 +58,17 @@ impl Indexes {
 
     fn remove(&mut self, path: &Utf8Path) {
         self.srch.remove(path);
+        self.cache.remove(path);
         let _ = self.fs.remove(path);
     }
 
     fn insert_srch(&mut self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
-        let recipe = RecipeEntry::new(path).read()?.parse(&self.parser);
-        let mut ingredients = Vec::new();
-        let mut cookware = Vec::new();
-        let mut metadata = None;
-        if let Some(r) = recipe.valid_output() {
-            metadata = Some(r.metadata.to_owned());
-            for ingredient in &r.ingredients {
-                ingredients.push(ingredient.name.to_owned());
-            }
-            for tool in &r.cookware {
-                cookware.push(tool.name.to_string());
-            }
-        }
-        self.srch.insert(
-            path.to_owned(),
-            RecipeData {
-                metadata,
-                ingredients,
-                cookware,
-            },
-        );
+        let mut data = self
+            .cache
+            .get(&RecipeEntry::new(path), &self.parser)?
+            .clone();
+        data.modified = self.cache.modified(path);
+        self.srch.insert(path.to_owned(), data);
         Ok(())
     }
 
@@ -114,18 +89,50 @@ pub enum Update {
     Renamed { from: Utf8PathBuf, to: Utf8PathBuf },
 }
 
+/// Time without changes before saving the cache
+///
+/// Saving writes the whole cache, so it's not done for every change.
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// Writes the cache without blocking the index for writing
+async fn save_cache(indexes: &Arc<RwLock<Indexes>>) {
+    if !indexes.write().await.cache.take_changes() {
+        return;
+    }
+    let indexes = Arc::clone(indexes);
+    let _ = tokio::task::spawn_blocking(move || indexes.blocking_read().cache.write()).await;
+}
+
 impl AsyncFsIndex {
-    pub fn new(index: FsIndex, parser: Arc<CooklangParser>) -> (Self, broadcast::Receiver<Update>) {
+    pub fn new(
+        index: FsIndex,
+        parser: Arc<CooklangParser>,
+        cache: RecipeCache,
+    ) -> (Self, broadcast::Receiver<Update>) {
         let (in_updt_tx, mut in_updt_rx) = mpsc::channel::<Update>(1);
         let (out_updates_tx, out_updates_rx) = broadcast::channel::<Update>(1);
         watch_changes_task(in_updt_tx, index.base_path());
 
-        let indexes = Arc::new(RwLock::new(Indexes::new(index, parser)));
+        let indexes = Arc::new(RwLock::new(Indexes::new(index, parser, cache)));
 
         let indexes2 = Arc::clone(&indexes);
         tokio::spawn(async move {
             let indexes = indexes2;
-            while let Some(update) = in_updt_rx.recv().await {
+            let mut unsaved = false;
+            loop {
+                let update = if unsaved {
+                    match tokio::time::timeout(SAVE_DELAY, in_updt_rx.recv()).await {
+                        Ok(update) => update,
+                        Err(_) => {
+                            save_cache(&indexes).await;
+                            unsaved = false;
+                            continue;
+                        }
+                    }
+                } else {
+                    in_updt_rx.recv().await
+                };
+                let Some(update) = update else { break };
                 match &update {
                     Update::Modified { path } => {
                         let mut indexes = indexes.write().await;
@@ -152,6 +159,7 @@ impl AsyncFsIndex {
                         let _ = indexes.insert(to);
                     }
                 }
+                unsaved = true;
                 // resend update after index is updated
                 let _ = out_updates_tx.send(update);
             }
@@ -170,20 +178,30 @@ impl AsyncFsIndex {
         indexes.fs.resolve(recipe, relative_to)
     }
 
+    /// Adds a recipe created by the server
+    ///
+    /// The file watcher would add it too, but with some delay. `path` has to
+    /// be prefixed with the base path.
+    pub async fn insert(&self, path: &Utf8Path) -> Result<(), cooklang_fs::Error> {
+        self.indexes.write().await.insert(path)
+    }
+
     pub async fn get(&self, recipe: &str) -> Result<RecipeEntry, cooklang_fs::Error> {
         let indexes = self.indexes.read().await;
         indexes.fs.get(recipe)
     }
 
+    /// Recipes that match `pred`, sorted, and the total number of matches
     pub async fn search<T>(
         &self,
         pred: impl Fn(&RecipeEntry, Option<&RecipeData>) -> bool,
         map: impl Fn(RecipeEntry, Option<&RecipeData>) -> T,
+        sort: SortBy,
         skip: usize,
         take: usize,
-    ) -> Vec<T> {
+    ) -> (Vec<T>, usize) {
         let indexes = self.indexes.read().await;
-        indexes
+        let mut matches = indexes
             .fs
             .get_all()
             .filter_map(|entry| {
@@ -193,14 +211,60 @@ impl AsyncFsIndex {
                     false => None,
                 }
             })
+            .collect::<Vec<_>>();
+        let total = matches.len();
+
+        // stable, ties keep the collection order
+        match sort {
+            SortBy::Path => {}
+            SortBy::Name => matches.sort_by_cached_key(|(entry, tokens)| {
+                tokens
+                    .and_then(|t| t.metadata.as_ref())
+                    .and_then(meta_name)
+                    .unwrap_or(entry.name())
+                    .to_lowercase()
+            }),
+            SortBy::Modified => {
+                matches.sort_by_key(|(_, tokens)| Reverse(tokens.and_then(|t| t.modified)))
+            }
+            SortBy::Time => matches.sort_by_cached_key(|(_, tokens)| {
+                let time = tokens
+                    .and_then(|t| t.metadata.as_ref())
+                    .and_then(|m| m.time())
+                    .map(|t| t.total());
+                (time.is_none(), time)
+            }),
+        }
+
+        let items = matches
+            .into_iter()
             .skip(skip)
             .take(take)
             .map(|(entry, meta)| map(entry, meta))
-            .collect()
+            .collect();
+        (items, total)
     }
 }
 
-fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
+/// Order of the search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Same as the collection, by path
+    #[default]
+    Path,
+    /// By name, ignoring case
+    Name,
+    /// Most recently modified first
+    Modified,
+    /// Shortest total time first, the recipes without time last
+    Time,
+}
+
+/// Sends an [`Update`] when a recipe in `base_path` changes
+///
+/// Must be called inside a tokio runtime.
+pub(crate) fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
     let watched_path = base_path.canonicalize().expect("Bad base path");
     let base_path = base_path.to_owned();
 
@@ -229,6 +293,7 @@ fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
         // canonicalized based path then this restores the path prefixed with
         // the base path not canonicalized
         let restore_path = |p| base_path.join(p);
+        let ignored = |p: &Utf8Path| cooklang_fs::is_ignored(&base_path, p);
 
         while let Some(res) = w_rx.recv().await {
             let ev = match res {
@@ -238,7 +303,7 @@ fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
                     continue;
                 }
             };
-            let paths = iter_paths(&watched_path, &ev.paths);
+            let paths = iter_paths(&watched_path, &ev.paths).filter(|p| !ignored(p));
             match ev.kind {
                 notify::EventKind::Create(_) => {
                     for path in paths {
@@ -251,10 +316,20 @@ fn watch_changes_task(tx: mpsc::Sender<Update>, base_path: &Utf8Path) {
                     if let Some((from, to)) =
                         handle_rename(&ev.paths, rename, &mut w_rx, &watched_path).await
                     {
-                        send(Update::Renamed {
-                            from: restore_path(from),
-                            to: restore_path(to),
-                        })
+                        // moving in or out of an ignored dir
+                        match (ignored(&from), ignored(&to)) {
+                            (false, false) => send(Update::Renamed {
+                                from: restore_path(from),
+                                to: restore_path(to),
+                            }),
+                            (false, true) => send(Update::Deleted {
+                                path: restore_path(from),
+                            }),
+                            (true, false) => send(Update::Added {
+                                path: restore_path(to),
+                            }),
+                            (true, true) => {}
+                        }
                     } else {
                         // fallback
                         for path in paths {
@@ -0,0 +1,327 @@
+//! Optional access control for the web UI and the API, from `[auth]`
+//!
+//! Browsers log in from `/login` with a user name and password or a token,
+//! and keep a session cookie. Other clients send the password with HTTP Basic
+//! or the token as a bearer token. Sessions are kept in memory, so they end
+//! when the server stops.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use argon2::{
+    password_hash::{rand_core::OsRng, rand_core::RngCore, SaltString},
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{AUTHORIZATION, HOST, ORIGIN, REFERER, WWW_AUTHENTICATE},
+        HeaderMap, Method, StatusCode, Uri,
+    },
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use once_cell::sync::Lazy;
+use tokio::task::block_in_place;
+
+use crate::config::{AuthConfig, Role};
+
+use super::{get_cookie, S};
+
+const SESSION_COOKIE: &str = "chef_session";
+const SESSION_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const MAX_SESSIONS: usize = 1000;
+/// Checked Basic credentials are hashed again after this
+const VERIFIED_AGE: Duration = Duration::from_secs(60 * 60);
+const MAX_VERIFIED: usize = 100;
+/// Wait before checking a login, for each recent failure from the same IP
+const FAILURE_DELAY: Duration = Duration::from_secs(1);
+const MAX_FAILURE_DELAY: Duration = Duration::from_secs(10);
+/// Failures are forgotten after this without more
+const FAILURE_AGE: Duration = Duration::from_secs(15 * 60);
+const MAX_FAILURES: usize = 1000;
+
+/// Checked for unknown users, so they take as long as a wrong password
+static DUMMY_HASH: Lazy<String> =
+    Lazy::new(|| hash_password("not a password").expect("failed to hash the dummy password"));
+
+pub struct Auth {
+    config: AuthConfig,
+    /// Cookies only over HTTPS
+    secure: bool,
+    /// Session id and its role
+    sessions: Mutex<HashMap<String, (Role, Instant)>>,
+    /// Basic credentials already checked, hashing is slow on purpose
+    verified: Mutex<HashMap<String, (Role, Instant)>>,
+    /// Failed logins by IP and the time of the last one
+    failures: Mutex<HashMap<IpAddr, (u32, Instant)>>,
+}
+
+impl Auth {
+    pub fn new(config: AuthConfig, secure: bool) -> Self {
+        if !config.users.is_empty() {
+            // or the first unknown user would take longer
+            Lazy::force(&DUMMY_HASH);
+        }
+        Self {
+            config,
+            secure,
+            sessions: Default::default(),
+            verified: Default::default(),
+            failures: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.is_empty()
+    }
+
+    /// The role of the request, `None` if it's not authenticated
+    fn role(&self, headers: &HeaderMap) -> Option<Role> {
+        if !self.is_enabled() {
+            return Some(Role::Write);
+        }
+        if let Some(role) = get_cookie(headers, SESSION_COOKIE).and_then(|id| self.session(id)) {
+            return Some(role);
+        }
+        let auth = headers.get(AUTHORIZATION)?.to_str().ok()?;
+        if let Some(token) = auth.strip_prefix("Bearer ") {
+            self.check_token(token.trim())
+        } else if let Some(credentials) = auth.strip_prefix("Basic ") {
+            self.check_basic(credentials.trim())
+        } else {
+            None
+        }
+    }
+
+    pub fn check_token(&self, token: &str) -> Option<Role> {
+        self.config
+            .tokens
+            .iter()
+            .find(|t| constant_time_eq(t.token.as_bytes(), token.as_bytes()))
+            .map(|t| t.role)
+    }
+
+    /// Checks a user password, this is slow on purpose
+    pub fn check_password(&self, name: &str, password: &str) -> Option<Role> {
+        let Some(user) = self.config.users.get(name) else {
+            // don't tell which users exist by answering faster
+            if let Ok(hash) = PasswordHash::new(&DUMMY_HASH) {
+                let _ = Argon2::default().verify_password(password.as_bytes(), &hash);
+            }
+            return None;
+        };
+        let hash = match PasswordHash::new(&user.password) {
+            Ok(hash) => hash,
+            Err(err) => {
+                tracing::error!("Invalid password hash for user '{name}': {err}");
+                return None;
+            }
+        };
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .ok()?;
+        Some(user.role)
+    }
+
+    fn check_basic(&self, credentials: &str) -> Option<Role> {
+        if let Some((role, checked)) = self.verified.lock().unwrap().get(credentials) {
+            if checked.elapsed() <= VERIFIED_AGE {
+                return Some(*role);
+            }
+        }
+        let decoded = STANDARD.decode(credentials).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (name, password) = decoded.split_once(':')?;
+        let role = self.check_password(name, password)?;
+        let mut verified = self.verified.lock().unwrap();
+        prune(&mut verified, VERIFIED_AGE, MAX_VERIFIED);
+        verified.insert(credentials.to_string(), (role, Instant::now()));
+        Some(role)
+    }
+
+    pub fn has_users(&self) -> bool {
+        !self.config.users.is_empty()
+    }
+
+    pub fn has_tokens(&self) -> bool {
+        !self.config.tokens.is_empty()
+    }
+
+    fn session(&self, id: &str) -> Option<Role> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let (role, created) = *sessions.get(id)?;
+        if created.elapsed() > SESSION_AGE {
+            sessions.remove(id);
+            return None;
+        }
+        Some(role)
+    }
+
+    pub fn is_logged_in(&self, headers: &HeaderMap) -> bool {
+        get_cookie(headers, SESSION_COOKIE).is_some_and(|id| self.session(id).is_some())
+    }
+
+    /// Starts a session and returns its id
+    pub fn login(&self, role: Role) -> String {
+        let mut bytes = [0; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let id = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let mut sessions = self.sessions.lock().unwrap();
+        prune(&mut sessions, SESSION_AGE, MAX_SESSIONS);
+        sessions.insert(id.clone(), (role, Instant::now()));
+        id
+    }
+
+    /// `Set-Cookie` value for the session, empty to remove it
+    pub fn session_cookie(&self, id: &str) -> String {
+        let max_age = if id.is_empty() {
+            0
+        } else {
+            SESSION_AGE.as_secs()
+        };
+        let secure = if self.secure { "; Secure" } else { "" };
+        format!("{SESSION_COOKIE}={id}; Path=/; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}")
+    }
+
+    /// Time to wait before checking a login from `ip`, to slow down guessing
+    pub fn login_delay(&self, ip: IpAddr) -> Duration {
+        let failures = self.failures.lock().unwrap();
+        match failures.get(&ip) {
+            Some((count, last)) if last.elapsed() <= FAILURE_AGE => {
+                (FAILURE_DELAY * *count).min(MAX_FAILURE_DELAY)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Records the result of a login from `ip`
+    pub fn login_result(&self, ip: IpAddr, ok: bool) {
+        let mut failures = self.failures.lock().unwrap();
+        if ok {
+            failures.remove(&ip);
+            return;
+        }
+        let count = match failures.get(&ip) {
+            Some((count, last)) if last.elapsed() <= FAILURE_AGE => count + 1,
+            _ => 1,
+        };
+        prune(&mut failures, FAILURE_AGE, MAX_FAILURES);
+        failures.insert(ip, (count, Instant::now()));
+    }
+
+    pub fn logout(&self, headers: &HeaderMap) {
+        if let Some(id) = get_cookie(headers, SESSION_COOKIE) {
+            self.sessions.lock().unwrap().remove(id);
+        }
+    }
+}
+
+/// Removes the expired entries, and the oldest one if there's no room for
+/// another
+fn prune<K: Clone + Eq + Hash, V>(map: &mut HashMap<K, (V, Instant)>, age: Duration, max: usize) {
+    map.retain(|_, (_, created)| created.elapsed() <= age);
+    if map.len() >= max {
+        let oldest = map
+            .iter()
+            .min_by_key(|(_, (_, created))| *created)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            map.remove(&oldest);
+        }
+    }
+}
+
+/// Hash for a user password in the config
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("failed to hash the password: {e}"))?;
+    Ok(hash.to_string())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Rejects requests that are not authenticated and adds their [`Role`]
+pub async fn authenticate(State(state): State<S>, mut req: Request, next: Next) -> Response {
+    match block_in_place(|| state.auth.role(req.headers())) {
+        Some(role) => {
+            req.extensions_mut().insert(role);
+            next.run(req).await
+        }
+        None => unauthorized(&state.auth.config, &req),
+    }
+}
+
+fn unauthorized(config: &AuthConfig, req: &Request) -> Response {
+    if req.uri().path().starts_with("/api/") {
+        let challenge = if config.users.is_empty() {
+            "Bearer"
+        } else {
+            "Basic realm=\"chef\", charset=\"UTF-8\""
+        };
+        let body = Json(serde_json::json!({ "error": "Unauthorized" }));
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(WWW_AUTHENTICATE, challenge)],
+            body,
+        )
+            .into_response();
+    }
+
+    let back = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    let back = percent_encoding::utf8_percent_encode(back, percent_encoding::NON_ALPHANUMERIC);
+    let url = format!("/login?next={back}");
+    if req.headers().contains_key("HX-Request") {
+        // htmx would follow a redirect and swap the login page into the target
+        return (StatusCode::UNAUTHORIZED, [("HX-Redirect", url)]).into_response();
+    }
+    Redirect::to(&url).into_response()
+}
+
+/// Rejects requests that change something from other sites
+///
+/// Browsers send cookies and cached Basic credentials with a form posted from
+/// any page, but they also say where it comes from. Clients that send neither
+/// `Origin` nor `Referer` are not browsers, so they are let through.
+pub async fn check_origin(req: Request, next: Next) -> Response {
+    if matches!(
+        req.method(),
+        &Method::GET | &Method::HEAD | &Method::OPTIONS
+    ) {
+        return next.run(req).await;
+    }
+    let headers = req.headers();
+    let Some(source) = headers.get(ORIGIN).or_else(|| headers.get(REFERER)) else {
+        return next.run(req).await;
+    };
+    let source = source
+        .to_str()
+        .ok()
+        .and_then(|s| s.parse::<Uri>().ok())
+        .and_then(|uri| uri.authority().map(|a| a.as_str().to_owned()));
+    let host = headers.get(HOST).and_then(|h| h.to_str().ok());
+    match (source, host) {
+        (Some(source), Some(host)) if source.eq_ignore_ascii_case(host) => next.run(req).await,
+        _ => StatusCode::FORBIDDEN.into_response(),
+    }
+}
+
+/// Only lets [`Role::Write`] through, must run after [`authenticate`]
+pub async fn require_write(req: Request, next: Next) -> Response {
+    match req.extensions().get::<Role>() {
+        Some(Role::Write) => next.run(req).await,
+        _ => StatusCode::FORBIDDEN.into_response(),
+    }
+}
@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use minijinja::context;
+
+use crate::cmd::serve::{locale::UserLocale, S};
+
+use super::{
+    check_path, ok_status,
+    recipe::{render_recipe, RecipeQuery},
+};
+
+/// The recipe one step at a time, to follow it while cooking
+pub async fn cook(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    Query(query): Query<RecipeQuery>,
+    UserLocale(t): UserLocale,
+) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+
+    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
+    let content = ok_status!(tokio::fs::read_to_string(entry.path()).await, NOT_FOUND);
+
+    let ctx = context! {
+        t,
+        wide => true,
+    };
+    render_recipe(&state, &entry, &content, query, "cook.html", ctx).await
+}
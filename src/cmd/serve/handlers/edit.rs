@@ -0,0 +1,177 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use minijinja::context;
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::serve::{get_cookie, locale::UserLocale, S},
+    util::write_atomic,
+};
+
+use super::{
+    check_path, clean_path, encode_path, mj_ok, ok_status,
+    recipe::{render_recipe, RecipeQuery},
+};
+
+/// Identifies the content of the file when the editor was loaded
+fn version(content: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+pub async fn edit(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    UserLocale(t): UserLocale,
+) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
+    let content = ok_status!(tokio::fs::read_to_string(entry.path()).await, NOT_FOUND);
+
+    // steps that can have an image
+    let steps = block_in_place(|| {
+        let Some(recipe) = state.parser.parse(&content).into_output() else {
+            return Vec::new();
+        };
+        let mut steps = Vec::new();
+        for (index, section) in recipe.sections.iter().enumerate() {
+            for content in &section.content {
+                if let cooklang::Content::Step(step) = content {
+                    steps.push(context! {
+                        value => format!("{index}.{}", step.number - 1),
+                        section => section.name,
+                        number => step.number,
+                    });
+                }
+            }
+        }
+        steps
+    });
+
+    let src_path = clean_path(entry.path(), &state.base_path);
+    let path = encode_path(src_path.with_extension("").as_str());
+    let tmpl = mj_ok!(state.templates.get_template("edit.html"));
+    let html = mj_ok!(tmpl.render(context! {
+        t,
+        name => entry.name(),
+        href => format!("/r/{path}"),
+        edit_href => format!("/edit/{path}"),
+        preview_href => format!("/edit_preview/{path}"),
+        upload_href => format!("/upload_image/{path}"),
+        steps,
+        version => version(&content),
+        content,
+        wide => true,
+    }));
+    Html(html).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PreviewForm {
+    content: String,
+}
+
+/// Renders the text in the editor, without saving it
+pub async fn edit_preview(
+    headers: HeaderMap,
+    State(state): State<S>,
+    Path(path): Path<String>,
+    UserLocale(t): UserLocale,
+    Form(form): Form<PreviewForm>,
+) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
+
+    let ctx = context! {
+        t,
+        path => format!("/r/{}", encode_path(&path)),
+        in_editor => true,
+        igr_layout => get_cookie(&headers, "igr_layout").unwrap_or("line"),
+    };
+    render_recipe(
+        &state,
+        &entry,
+        &form.content,
+        RecipeQuery::default(),
+        "components/recipe_content.html",
+        ctx,
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct SaveForm {
+    content: String,
+    /// Version of the file the edit started from
+    version: String,
+}
+
+/// Saves the editor text, if the file didn't change since it was loaded
+pub async fn edit_save(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    UserLocale(t): UserLocale,
+    Form(form): Form<SaveForm>,
+) -> Response {
+    let toast_html = |text_key: &str, color: &str, version: Option<&str>| {
+        let tmpl = state.templates.get_template("components/edit_saved.html")?;
+        let html = tmpl.render(context! {
+            t,
+            text_key,
+            color,
+            version,
+        })?;
+        Ok::<_, minijinja::Error>(Html(html))
+    };
+    let err_html = || toast_html("edit.error", "red", None);
+
+    if let Err(err) = check_path(&state, &path) {
+        return (err, mj_ok!(err_html())).into_response();
+    }
+    let entry = match state.recipe_index.get(&path).await {
+        Ok(entry) => entry,
+        Err(_) => return (StatusCode::NOT_FOUND, mj_ok!(err_html())).into_response(),
+    };
+    let current = match tokio::fs::read_to_string(entry.path()).await {
+        Ok(current) => current,
+        Err(_) => return (StatusCode::NOT_FOUND, mj_ok!(err_html())).into_response(),
+    };
+    if version(&current) != form.version {
+        tracing::warn!("Not saving '{}': changed on disk", entry.path());
+        let html = mj_ok!(toast_html("edit.conflict", "red", None));
+        return (StatusCode::CONFLICT, html).into_response();
+    }
+
+    // browsers send the textarea with CRLF line endings
+    let content = if current.contains("\r\n") {
+        form.content
+    } else {
+        form.content.replace("\r\n", "\n")
+    };
+
+    tracing::info!("Saving '{}' from the editor", entry.path());
+    let path = entry.path().to_owned();
+    let data = content.clone();
+    let res = tokio::task::spawn_blocking(move || write_atomic(&path, data.as_bytes()))
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|r| r);
+    if let Err(err) = res {
+        tracing::error!("Failed to save '{}': {err}", entry.path());
+        return (StatusCode::INTERNAL_SERVER_ERROR, mj_ok!(err_html())).into_response();
+    }
+
+    mj_ok!(toast_html("edit.saved", "green", Some(&version(&content)))).into_response()
+}
@@ -0,0 +1,62 @@
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use camino::Utf8PathBuf;
+use tower::ServiceExt;
+
+use crate::cmd::serve::{
+    thumbnails::{self, Thumbnail},
+    S,
+};
+
+use super::{check_path, ok_status};
+
+pub async fn image(
+    State(state): State<S>,
+    Path((width, path)): Path<(u32, String)>,
+    req: Request,
+) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+    let path = Utf8PathBuf::from(path);
+    let is_image = path
+        .extension()
+        .is_some_and(|ext| cooklang_fs::IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+    if !thumbnails::WIDTHS.contains(&width)
+        || !is_image
+        || cooklang_fs::is_ignored(&state.base_path, &path)
+        || !state.base_path.join(&path).is_file()
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if !thumbnails::is_supported(&path) {
+        let ext = path.extension().unwrap_or_default();
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("Can't resize '{ext}' images, convert '{path}' to JPEG or PNG"),
+        )
+            .into_response();
+    }
+
+    let state2 = S::clone(&state);
+    let thumbnail = tokio::task::spawn_blocking(move || state2.thumbnails.get(&path, width)).await;
+    let thumbnail = ok_status!(ok_status!(thumbnail));
+
+    match thumbnail {
+        Thumbnail::File(file) => {
+            // handles the conditional requests
+            let res = tower_http::services::ServeFile::new(file)
+                .oneshot(req)
+                .await;
+            match res {
+                Ok(res) => res.map(Body::new),
+                Err(never) => match never {},
+            }
+        }
+        Thumbnail::Memory { data, mime } => ([(header::CONTENT_TYPE, mime)], data).into_response(),
+    }
+}
@@ -27,7 +27,7 @@ pub async fn index(
 ) -> Response {
     let mut path = Utf8PathBuf::from(&state.base_path);
     if let Some(Path(p)) = &requested_path {
-        match check_path(p) {
+        match check_path(&state, p) {
             Ok(_) => {
                 path = path.join(p);
             }
@@ -35,7 +35,7 @@ pub async fn index(
         }
     }
 
-    let entries = match cooklang_fs::walk_dir(&path) {
+    let entries = match cooklang_fs::walk_dir_with(&state.base_path, &path, &state.symlinks) {
         Ok(entries) => entries,
         Err(err) => {
             let status = if err.kind() == io::ErrorKind::NotFound {
@@ -56,26 +56,10 @@ pub async fn index(
                 path => clean_path(dir.path(), &state.base_path)
             }),
             cooklang_fs::Entry::Recipe(r) => {
-                let tokens = r.read().ok().map(|c| {
-                    let recipe = c.parse(&state.parser);
-                    let mut ingredients = Vec::new();
-                    let mut cookware = Vec::new();
-                    let mut metadata = None;
-                    if let Some(r) = recipe.valid_output() {
-                        metadata = Some(r.metadata.to_owned());
-                        for ingredient in &r.ingredients {
-                            ingredients.push(ingredient.name.to_owned());
-                        }
-                        for tool in &r.cookware {
-                            cookware.push(tool.name.to_string());
-                        }
-                    }
-                    RecipeData {
-                        metadata,
-                        ingredients,
-                        cookware,
-                    }
-                });
+                let tokens = r
+                    .read()
+                    .ok()
+                    .map(|c| RecipeData::parse(&c.into_text(), &state.parser));
                 recipes.push(recipe_entry_context(r, &state, tokens.as_ref()).unwrap());
             }
         }
@@ -0,0 +1,108 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{header::SET_COOKIE, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use minijinja::{context, Value};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::cmd::serve::{locale::UserLocale, AppState, S};
+
+use super::mj_ok;
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    /// Page to go back to
+    #[serde(default)]
+    next: String,
+}
+
+pub async fn login(
+    State(state): State<S>,
+    headers: HeaderMap,
+    UserLocale(t): UserLocale,
+    Query(query): Query<LoginQuery>,
+) -> Response {
+    if !state.auth.is_enabled() {
+        return Redirect::to("/").into_response();
+    }
+    let logged_in = state.auth.is_logged_in(&headers);
+    render_login(&state, t, &query.next, logged_in, None)
+}
+
+fn render_login(
+    state: &AppState,
+    t: Value,
+    next: &str,
+    logged_in: bool,
+    error: Option<&str>,
+) -> Response {
+    let tmpl = mj_ok!(state.templates.get_template("login.html"));
+    let html = mj_ok!(tmpl.render(context! {
+        t,
+        next,
+        logged_in,
+        error,
+        has_users => state.auth.has_users(),
+        has_tokens => state.auth.has_tokens(),
+    }));
+    Html(html).into_response()
+}
+
+/// A user and password, or a token
+#[derive(Deserialize)]
+pub struct LoginForm {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    next: String,
+}
+
+/// Starts a session with one of the users or tokens in the config
+///
+/// Each failure from the same IP makes the next attempts wait longer.
+pub async fn login_submit(
+    State(state): State<S>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    UserLocale(t): UserLocale,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let ip = addr.ip();
+    tokio::time::sleep(state.auth.login_delay(ip)).await;
+    let role = if form.name.is_empty() {
+        state.auth.check_token(form.token.trim())
+    } else {
+        block_in_place(|| state.auth.check_password(&form.name, &form.password))
+    };
+    state.auth.login_result(ip, role.is_some());
+    let Some(role) = role else {
+        let page = render_login(&state, t, &form.next, false, Some("login.error"));
+        return (StatusCode::UNAUTHORIZED, page).into_response();
+    };
+    let id = state.auth.login(role);
+    let cookie = state.auth.session_cookie(&id);
+    ([(SET_COOKIE, cookie)], Redirect::to(local_url(&form.next))).into_response()
+}
+
+pub async fn logout(State(state): State<S>, headers: HeaderMap) -> Response {
+    state.auth.logout(&headers);
+    let cookie = state.auth.session_cookie("");
+    ([(SET_COOKIE, cookie)], Redirect::to("/login")).into_response()
+}
+
+/// Only redirects inside the site
+fn local_url(url: &str) -> &str {
+    if url.starts_with('/') && !url.starts_with("//") && !url.starts_with("/\\") {
+        url
+    } else {
+        "/"
+    }
+}
@@ -1,29 +1,49 @@
+use std::collections::HashSet;
+
 use camino::{Utf8Path, Utf8PathBuf};
 use cooklang_fs::RecipeEntry;
 use minijinja::{context, Value};
+use serde::Serialize;
+use tokio::task::block_in_place;
 
 use crate::{config::UiConfig, util::meta_name};
 
 use super::async_index::RecipeData;
-use super::AppState;
+use super::{thumbnails, AppState};
 
 pub mod about;
 pub mod convert_popover;
+pub mod cook;
+pub mod edit;
+pub mod image;
 pub mod index;
+pub mod login;
+pub mod new_recipe;
 pub mod open_editor;
 pub mod recipe;
 pub mod search;
+pub mod shopping_list;
 pub mod sse_updates;
 pub mod static_file;
+pub mod stats;
+pub mod suggest;
 
 pub use about::about;
 pub use convert_popover::convert_popover;
+pub use cook::cook;
+pub use edit::{edit, edit_preview, edit_save};
+pub use image::image;
 pub use index::index;
+pub use login::{login, login_submit, logout};
+pub use new_recipe::{create_recipe, new_recipe, upload_image};
 pub use open_editor::open_editor;
 pub use recipe::recipe;
-pub use search::search;
+pub use search::{search, SearchQuery};
+pub use shopping_list::{list_add, list_check, list_clear, list_remove, shopping_list};
 pub use sse_updates::sse_updates;
 pub use static_file::static_file;
+pub use stats::stats;
+pub use suggest::suggest;
 
 macro_rules! ok_status {
     ($res:expr) => {
@@ -63,7 +83,10 @@ macro_rules! mj_ok {
 }
 pub(crate) use mj_ok;
 
-pub fn check_path(p: &str) -> Result<(), axum::http::StatusCode> {
+/// Checks a path from the url is a relative path inside the collection
+///
+/// Links that escape the collection are rejected as well.
+pub fn check_path(state: &AppState, p: &str) -> Result<(), axum::http::StatusCode> {
     let path = camino::Utf8Path::new(p);
     if !path.components().all(|c| match c {
         camino::Utf8Component::Normal(comp) => {
@@ -77,10 +100,16 @@ pub fn check_path(p: &str) -> Result<(), axum::http::StatusCode> {
     }) {
         return Err(axum::http::StatusCode::BAD_REQUEST);
     }
+    if !state
+        .symlinks
+        .is_contained(&state.base_path, &state.base_path.join(path))
+    {
+        return Err(axum::http::StatusCode::NOT_FOUND);
+    }
     Ok(())
 }
 
-fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
+pub(crate) fn clean_path(p: &Utf8Path, base_path: &Utf8Path) -> Utf8PathBuf {
     let p = p
         .strip_prefix(base_path)
         .expect("dir entry path not relative to base path");
@@ -108,7 +137,7 @@ fn recipe_entry_context(
                 .map(|t| tag_context(t.as_str(), &state.config.ui)),
         );
         if let Some(external_image) = m.map.get("image") {
-            image = Some(external_image.clone());
+            image = Some(context! { src => external_image });
         }
 
         let name = meta_name(m).unwrap_or(r.name()).to_string();
@@ -127,22 +156,59 @@ fn recipe_entry_context(
             .images()
             .iter()
             .find(|i| i.indexes.is_none())
-            .map(|i| image_url(&i.path, &state.base_path));
+            .map(|i| image_context(&i.path, &state.base_path));
     }
 
     let path = clean_path(r.path(), &state.base_path).with_extension("");
 
     Some(context! {
         fallback_name => r.name(),
-        href => format!("/r/{path}"),
+        href => format!("/r/{}", encode_path(path.as_str())),
         error,
         image,
         ..metadata,
     })
 }
 
-fn image_url(path: &Utf8Path, base_path: &Utf8Path) -> String {
-    format!("/src/{}", clean_path(path, base_path))
+/// `src` and `srcset` of an image in the collection
+///
+/// If thumbnails can't be made, `src` is the original image and `unsupported`
+/// is set.
+fn image_context(path: &Utf8Path, base_path: &Utf8Path) -> Value {
+    match image_urls(path, base_path) {
+        (src, Some(srcset)) => context! { src, srcset },
+        (src, None) => context! { src, unsupported => true },
+    }
+}
+
+/// Url of the default thumbnail of an image and its `srcset`
+///
+/// If thumbnails can't be made, the url is the original image and there's no
+/// `srcset`.
+pub fn image_urls(path: &Utf8Path, base_path: &Utf8Path) -> (String, Option<String>) {
+    let rel = clean_path(path, base_path);
+    let encoded = encode_path(rel.as_str());
+    if !thumbnails::is_supported(&rel) {
+        return (format!("/src/{encoded}"), None);
+    }
+    (
+        thumbnails::url(&encoded, thumbnails::DEFAULT_WIDTH),
+        Some(thumbnails::srcset(&encoded)),
+    )
+}
+
+/// Characters that would break a path inside an URL or `srcset`
+const URL_PATH: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b',')
+    .add(b'"')
+    .add(b'#')
+    .add(b'?')
+    .add(b'%');
+
+/// Escapes a path of the collection to use it in an URL
+pub fn encode_path(path: &str) -> String {
+    percent_encoding::utf8_percent_encode(path, URL_PATH).to_string()
 }
 
 fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
@@ -161,8 +227,88 @@ fn tag_context(name: &str, ui_config: &UiConfig) -> Value {
     context! { emoji, name }
 }
 
+/// A folder of the collection with its subfolders and recipes
+#[derive(Serialize)]
+pub(crate) struct Folder {
+    pub name: String,
+    /// Relative to the collection
+    pub path: Utf8PathBuf,
+    pub folders: Vec<Folder>,
+    pub recipes: Vec<FolderRecipe>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct FolderRecipe {
+    pub name: String,
+    /// Relative to the collection, without extension
+    pub path: Utf8PathBuf,
+}
+
+impl Folder {
+    /// Walks `dir` and its subfolders, up to the max depth of the config
+    ///
+    /// This blocks while reading the dirs.
+    pub(crate) fn walk(state: &AppState, dir: &Utf8Path) -> std::io::Result<Self> {
+        let mut visited = HashSet::new();
+        Self::walk_inner(state, dir, 0, &mut visited)
+    }
+
+    fn walk_inner(
+        state: &AppState,
+        dir: &Utf8Path,
+        depth: usize,
+        visited: &mut HashSet<std::path::PathBuf>,
+    ) -> std::io::Result<Self> {
+        let entries = cooklang_fs::walk_dir_with(&state.base_path, dir, &state.symlinks)?;
+        if let Ok(real) = dir.canonicalize() {
+            visited.insert(real);
+        }
+
+        let mut folder = Self {
+            name: dir
+                .strip_prefix(&state.base_path)
+                .ok()
+                .and_then(|p| p.file_name())
+                .unwrap_or_default()
+                .to_string(),
+            path: clean_path(dir, &state.base_path),
+            folders: Vec::new(),
+            recipes: Vec::new(),
+        };
+        for entry in entries {
+            match entry {
+                cooklang_fs::Entry::Dir(d) => {
+                    // links can make the same folder appear twice
+                    let seen = d
+                        .path()
+                        .canonicalize()
+                        .is_ok_and(|real| visited.contains(&real));
+                    if depth < state.config.max_depth && !seen {
+                        let sub = Self::walk_inner(state, d.path(), depth + 1, visited)?;
+                        folder.folders.push(sub);
+                    }
+                }
+                cooklang_fs::Entry::Recipe(r) => folder.recipes.push(FolderRecipe {
+                    name: r.name().to_string(),
+                    path: clean_path(r.path(), &state.base_path).with_extension(""),
+                }),
+            }
+        }
+        Ok(folder)
+    }
+
+    /// Paths of this folder and all its subfolders, depth first
+    pub(crate) fn paths(&self) -> Vec<&Utf8Path> {
+        let mut paths = vec![self.path.as_path()];
+        for sub in &self.folders {
+            paths.extend(sub.paths());
+        }
+        paths
+    }
+}
+
 #[derive(Debug)]
-enum Searcher {
+pub(crate) enum Searcher {
     All(Vec<Self>),
     Any(Vec<Self>),
     Not(Box<Self>),
@@ -170,27 +316,102 @@ enum Searcher {
     Tag(String),
     Ingredient(String),
     Cookware(String),
+    /// Total time in minutes, from `time` or `prep_time` and `cook_time`
+    Time(Compare, u32),
+    Servings(Compare, u32),
+    Author(String),
+    Source(String),
+    /// Folder relative to the collection, with its subfolders
+    Folder(String),
+    HasImage,
+    HasError,
+}
+
+/// How a number in the search is compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compare {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Compare {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Compare::Eq => "",
+            Compare::Lt => "<",
+            Compare::Le => "<=",
+            Compare::Gt => ">",
+            Compare::Ge => ">=",
+        }
+    }
+
+    fn matches(self, value: u32, target: u32) -> bool {
+        match self {
+            Compare::Eq => value == target,
+            Compare::Lt => value < target,
+            Compare::Le => value <= target,
+            Compare::Gt => value > target,
+            Compare::Ge => value >= target,
+        }
+    }
 }
 
 impl Searcher {
-    fn matches_recipe(&self, name: &str, tokens: &RecipeData) -> bool {
+    pub(crate) fn matches_recipe(
+        &self,
+        entry: &RecipeEntry,
+        tokens: &RecipeData,
+        state: &AppState,
+    ) -> bool {
+        let meta = tokens.metadata.as_ref();
+        let meta_text = |key: &str| meta.and_then(|m| m.map.get(key)).map(|v| v.to_lowercase());
         match self {
-            Self::All(v) => v.is_empty() | v.iter().all(|s| s.matches_recipe(name, tokens)),
-            Self::Any(v) => v.is_empty() | v.iter().any(|s| s.matches_recipe(name, tokens)),
-            Self::Not(searcher) => !searcher.matches_recipe(name, tokens),
-            Self::NamePart(part) => name.to_lowercase().contains(part),
-            Self::Tag(tag) => match tokens.metadata.as_ref() {
+            Self::All(v) => v.is_empty() | v.iter().all(|s| s.matches_recipe(entry, tokens, state)),
+            Self::Any(v) => v.is_empty() | v.iter().any(|s| s.matches_recipe(entry, tokens, state)),
+            Self::Not(searcher) => !searcher.matches_recipe(entry, tokens, state),
+            Self::NamePart(part) => {
+                let name = meta.and_then(meta_name).unwrap_or(entry.name());
+                name.to_lowercase().contains(part)
+            }
+            Self::Tag(tag) => match meta {
                 Some(meta) => meta.tags().unwrap_or(&[]).iter().any(|t| t.contains(tag)),
                 None => false,
             },
-            Self::Ingredient(ingredient) => tokens
-                .ingredients
-                .iter()
-                .any(|str| str.to_lowercase().contains(ingredient)),
+            Self::Ingredient(ingredient) => {
+                let synonyms = state.aliases.synonyms(ingredient);
+                tokens.ingredients.iter().any(|str| {
+                    let str = str.to_lowercase();
+                    str.contains(ingredient)
+                        || synonyms.iter().any(|s| str.contains(&s.to_lowercase()))
+                })
+            }
             Self::Cookware(cookware) => tokens
                 .cookware
                 .iter()
                 .any(|str| str.to_lowercase().contains(cookware)),
+            Self::Time(cmp, minutes) => meta
+                .and_then(|m| m.time())
+                .is_some_and(|t| cmp.matches(t.total(), *minutes)),
+            Self::Servings(cmp, n) => meta
+                .and_then(|m| m.servings())
+                .is_some_and(|s| s.iter().any(|s| cmp.matches(*s, *n))),
+            Self::Author(author) => meta_text("author").is_some_and(|a| a.contains(author)),
+            Self::Source(source) => meta_text("source").is_some_and(|s| s.contains(source)),
+            Self::Folder(folder) => {
+                let path = clean_path(entry.path(), &state.base_path);
+                let dir = path.parent().map(|p| p.as_str()).unwrap_or("");
+                let dir = dir.to_lowercase();
+                dir == *folder || dir.starts_with(&format!("{folder}/"))
+            }
+            Self::HasImage => {
+                // images are not in the index, this reads the recipe dir
+                meta_text("image").is_some()
+                    || block_in_place(|| entry.images().iter().any(|i| i.indexes.is_none()))
+            }
+            Self::HasError => tokens.errors > 0,
         }
     }
 }
@@ -0,0 +1,286 @@
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Context as _};
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use camino::Utf8PathBuf;
+use image::ImageFormat;
+use minijinja::{context, Value};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::{
+    cmd::{
+        new,
+        serve::{locale::UserLocale, AppState, S},
+    },
+    util::write_atomic,
+};
+
+use super::{check_path, clean_path, mj_ok, ok_status, Folder};
+
+/// Max size of an uploaded image
+pub const MAX_UPLOAD_SIZE: usize = 32 * 1024 * 1024;
+
+#[derive(Deserialize, Default)]
+pub struct NewForm {
+    /// Folder relative to the collection, empty for the root
+    #[serde(default)]
+    dir: String,
+    #[serde(default)]
+    name: String,
+    /// Empty for no template
+    #[serde(default)]
+    template: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewQuery {
+    #[serde(default)]
+    dir: String,
+}
+
+pub async fn new_recipe(
+    State(state): State<S>,
+    UserLocale(t): UserLocale,
+    Query(query): Query<NewQuery>,
+) -> Response {
+    let form = NewForm {
+        dir: query.dir,
+        ..Default::default()
+    };
+    render_form(&state, t, &form, None)
+}
+
+fn render_form(state: &AppState, t: Value, form: &NewForm, error: Option<String>) -> Response {
+    let tree = ok_status!(block_in_place(|| Folder::walk(state, &state.base_path)));
+    let folders = tree
+        .paths()
+        .into_iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>();
+
+    let tmpl = mj_ok!(state.templates.get_template("new.html"));
+    let html = mj_ok!(tmpl.render(context! {
+        t,
+        folders,
+        templates => new::templates(&state.base_path),
+        dir => form.dir.trim_matches('/'),
+        name => form.name,
+        template => form.template,
+        error,
+    }));
+    Html(html).into_response()
+}
+
+pub async fn create_recipe(
+    headers: HeaderMap,
+    State(state): State<S>,
+    UserLocale(t): UserLocale,
+    Form(form): Form<NewForm>,
+) -> Response {
+    if !form.template.is_empty() {
+        if let Err(err) = block_in_place(|| new::check_template(&state.base_path, &form.template)) {
+            let res = render_form(&state, t, &form, Some(format!("{err:#}")));
+            return (StatusCode::BAD_REQUEST, res).into_response();
+        }
+    }
+    let path = match block_in_place(|| create(&state, &form)) {
+        Ok(path) => path,
+        Err(err) => {
+            let res = render_form(&state, t, &form, Some(format!("{err:#}")));
+            return (StatusCode::UNPROCESSABLE_ENTITY, res).into_response();
+        }
+    };
+    tracing::info!("Created '{path}'");
+    if let Err(err) = state.recipe_index.insert(&path).await {
+        tracing::error!("Could not add '{path}' to the index: {err}");
+    }
+
+    let url = format!(
+        "/edit/{}",
+        clean_path(&path, &state.base_path).with_extension("")
+    );
+    if headers.contains_key("HX-Request") {
+        [("HX-Redirect", url)].into_response()
+    } else {
+        Redirect::to(&url).into_response()
+    }
+}
+
+/// Creates the recipe file and returns its path
+fn create(state: &AppState, form: &NewForm) -> anyhow::Result<Utf8PathBuf> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        bail!("The name can't be empty");
+    }
+    let dir = form.dir.trim_matches('/');
+    let valid_dir = dir.is_empty()
+        || (check_path(state, dir).is_ok()
+            && state.base_path.join(dir).is_dir()
+            && !cooklang_fs::is_ignored(&state.base_path, dir.into()));
+    if !valid_dir {
+        bail!("Invalid folder: {dir}");
+    }
+
+    let full_name = if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{dir}/{name}")
+    };
+    let path = new::recipe_path(&state.base_path, &full_name)?;
+    let rel = clean_path(&path, &state.base_path);
+    if check_path(state, rel.as_str()).is_err() {
+        bail!("Invalid name: {name}");
+    }
+    if cooklang_fs::is_ignored(&state.base_path, &path) {
+        bail!("'{rel}' would be ignored by a .chefignore file or a '.' in the name");
+    }
+
+    let stem = path.file_stem().unwrap_or(name);
+    let template = Some(form.template.as_str()).filter(|t| !t.is_empty());
+    let text = new::initial_text(
+        &state.base_path,
+        &state.config,
+        state.author.as_deref(),
+        template,
+        stem,
+    )?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // never overwrite, the file may have been created after the check
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .with_context(|| format!("Failed to create '{rel}'"))?;
+    file.write_all(text.as_bytes())?;
+    Ok(path)
+}
+
+/// Stores an image for a recipe
+///
+/// Multipart fields are `image` and `step`. `step` is `<section>.<step>`,
+/// with the indexes starting at 0, or empty for the main image.
+pub async fn upload_image(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    UserLocale(t): UserLocale,
+    mut multipart: Multipart,
+) -> Response {
+    let toast_html = |text_key: &str, color: &str| {
+        let tmpl = state.templates.get_template("components/oob_toast.html")?;
+        let html = tmpl.render(context! {
+            t,
+            text_key,
+            color,
+        })?;
+        Ok::<_, minijinja::Error>(Html(html))
+    };
+    let err_response = |status: StatusCode, text_key: &str| {
+        (status, mj_ok!(toast_html(text_key, "red"))).into_response()
+    };
+
+    if let Err(err) = check_path(&state, &path) {
+        return err_response(err, "upload.error");
+    }
+    let Ok(entry) = state.recipe_index.get(&path).await else {
+        return err_response(StatusCode::NOT_FOUND, "upload.error");
+    };
+
+    let mut step = String::new();
+    let mut upload = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => {
+                tracing::warn!("Bad image upload: {err}");
+                return err_response(err.status(), "upload.error");
+            }
+        };
+        match field.name() {
+            Some("step") => step = field.text().await.unwrap_or_default(),
+            Some("image") => {
+                let file_name = field.file_name().unwrap_or_default().to_string();
+                match field.bytes().await {
+                    Ok(data) => upload = Some((file_name, data)),
+                    Err(err) => {
+                        tracing::warn!("Bad image upload: {err}");
+                        return err_response(err.status(), "upload.error");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let Some((file_name, data)) = upload.filter(|(_, data)| !data.is_empty()) else {
+        return err_response(StatusCode::BAD_REQUEST, "upload.noFile");
+    };
+
+    let ext = match image::guess_format(&data) {
+        Ok(ImageFormat::Jpeg) => "jpg",
+        Ok(ImageFormat::Png) => "png",
+        Ok(ImageFormat::WebP) => "webp",
+        Ok(ImageFormat::Gif) => "gif",
+        // no decoder for heic, trust the name
+        _ if file_name.to_lowercase().ends_with(".heic") => "heic",
+        _ => return err_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, "upload.notImage"),
+    };
+    let indexes = match parse_step(&step) {
+        Ok(indexes) => indexes,
+        Err(err) => {
+            tracing::warn!("Bad image upload: {err}");
+            return err_response(StatusCode::BAD_REQUEST, "upload.error");
+        }
+    };
+
+    let recipe_path = entry.path().to_owned();
+    let stem = entry.path().file_stem().unwrap_or_default();
+    let target = entry.path().with_file_name(match indexes {
+        None => format!("{stem}.{ext}"),
+        Some((0, step)) => format!("{stem}.{step}.{ext}"),
+        Some((section, step)) => format!("{stem}.{section}.{step}.{ext}"),
+    });
+    tracing::info!("Saving image '{target}'");
+    let res = tokio::task::spawn_blocking(move || {
+        write_atomic(&target, &data)?;
+        // only one image for the same step
+        for img in cooklang_fs::recipe_images(&recipe_path) {
+            let same = img.indexes.map(|i| (i.section(), i.step())) == indexes;
+            if same && img.path != target {
+                std::fs::remove_file(&img.path)?;
+            }
+        }
+        Ok::<_, std::io::Error>(())
+    })
+    .await
+    .map_err(std::io::Error::other)
+    .and_then(|r| r);
+    if let Err(err) = res {
+        tracing::error!("Failed to save image: {err}");
+        return err_response(StatusCode::INTERNAL_SERVER_ERROR, "upload.error");
+    }
+
+    (
+        [("HX-Trigger", "image-uploaded")],
+        mj_ok!(toast_html("upload.saved", "green")),
+    )
+        .into_response()
+}
+
+fn parse_step(step: &str) -> anyhow::Result<Option<(u16, u16)>> {
+    if step.is_empty() {
+        return Ok(None);
+    }
+    let (section, step) = step
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid step: {step}"))?;
+    Ok(Some((section.parse()?, step.parse()?)))
+}
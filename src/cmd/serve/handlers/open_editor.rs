@@ -34,7 +34,7 @@ pub async fn open_editor(
 
     let err_html = || toast_html("openInEditor.error", "red");
 
-    if let Err(err) = check_path(&path) {
+    if let Err(err) = check_path(&state, &path) {
         return (err, mj_ok!(err_html())).into_response();
     }
 
@@ -7,6 +7,7 @@ use axum::{
 };
 use camino::Utf8Path;
 use cooklang::{error::SourceReport, Converter, Modifiers, ParseOptions, ScaledRecipe};
+use cooklang_fs::RecipeEntry;
 use minijinja::{context, Value};
 use serde::{Deserialize, Serialize};
 use tokio::task::block_in_place;
@@ -23,9 +24,9 @@ use crate::{
     RECIPE_REF_ERROR,
 };
 
-use super::{check_path, image_url, mj_ok};
+use super::{check_path, encode_path, image_context, mj_ok};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Default)]
 pub struct RecipeQuery {
     scale: Option<u32>,
     units: Option<String>,
@@ -39,6 +40,34 @@ pub async fn recipe(
     uri: Uri,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     UserLocale(t): UserLocale,
+) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+
+    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
+    let content = ok_status!(tokio::fs::read_to_string(&entry.path()).await, NOT_FOUND);
+
+    let ctx = context! {
+        t,
+        path => uri.path(),
+        is_loopback => addr.ip().is_loopback(),
+        igr_layout => get_cookie(&headers, "igr_layout").unwrap_or("line"),
+    };
+    render_recipe(&state, &entry, &content, query, "recipe.html", ctx).await
+}
+
+/// Renders a recipe, or its errors, with `content` as the recipe text
+///
+/// `ctx` is merged into the template context. The editor preview uses this
+/// with the text that is not saved yet.
+pub(crate) async fn render_recipe(
+    state: &AppState,
+    entry: &RecipeEntry,
+    content: &str,
+    query: RecipeQuery,
+    template: &str,
+    ctx: Value,
 ) -> Response {
     let units: Option<cooklang::convert::System> = match query.units.as_deref() {
         None => None,
@@ -49,28 +78,25 @@ pub async fn recipe(
         },
     };
 
-    if let Err(e) = check_path(&path) {
-        return e.into_response();
-    }
-
-    let entry = ok_status!(state.recipe_index.get(&path).await, NOT_FOUND);
-    let content = ok_status!(tokio::fs::read_to_string(&entry.path()).await, NOT_FOUND);
-
     let res = block_in_place(|| {
         state
             .parser
-            .parse_with_options(&content, state.parse_options(Some(entry.path())))
+            .parse_with_options(content, state.parse_options(Some(entry.path())))
             .into_result()
     });
 
-    let tmpl = mj_ok!(state.templates.get_template("recipe.html"));
+    let tmpl = mj_ok!(state.templates.get_template(template));
 
     let src_path = clean_path(entry.path(), &state.base_path);
+    let path = encode_path(src_path.with_extension("").as_str());
     let ctx = context! {
-        t,
         is_valid => res.is_ok(),
-        href => format!("/r/{}", src_path.with_extension("")),
+        href => format!("/r/{path}"),
+        edit_href => format!("/edit/{path}"),
+        list_add_href => format!("/list/add/{path}"),
+        cook_href => format!("/cook/{path}"),
         src_path,
+        ..ctx
     };
 
     match res {
@@ -93,7 +119,7 @@ pub async fn recipe(
                 Some(ok_status!(report_to_html(
                     &warnings,
                     entry.file_name(),
-                    &content
+                    content
                 )))
             };
 
@@ -118,7 +144,8 @@ pub async fn recipe(
                             Ok(entry) => {
                                 let path =
                                     clean_path(entry.path(), &state.base_path).with_extension("");
-                                let value = Value::from(format!("/r/{path}"));
+                                let value =
+                                    Value::from(format!("/r/{}", encode_path(path.as_str())));
                                 Some((igr.name.clone(), value))
                             }
                             Err(_) => None,
@@ -130,16 +157,17 @@ pub async fn recipe(
             let images = Value::from_iter(entry.images().iter().map(|img| {
                 context! {
                     indexes => img.indexes,
-                    href => image_url(&img.path, &state.base_path)
+                    ..image_context(&img.path, &state.base_path)
                 }
             }));
-            let main_image = scaled.metadata.map.get("image").cloned().or_else(|| {
-                entry
+            let main_image = match scaled.metadata.map.get("image") {
+                Some(external) => Some(context! { src => external }),
+                None => entry
                     .images()
                     .iter()
                     .find(|img| img.indexes.is_none())
-                    .map(|img| image_url(&img.path, &state.base_path))
-            });
+                    .map(|img| image_context(&img.path, &state.base_path)),
+            };
 
             let r = make_recipe_context(scaled, state.parser.converter(), &state.config);
 
@@ -147,16 +175,12 @@ pub async fn recipe(
                 name,
                 r,
                 query,
-                path => uri.path(),
                 recipe_refs,
 
                 times,
                 images,
                 main_image,
 
-                is_loopback => addr.ip().is_loopback(),
-                igr_layout => get_cookie(&headers, "igr_layout").unwrap_or("line"),
-
                 report_html,
                 severity => "warning",
                 ..ctx
@@ -165,7 +189,7 @@ pub async fn recipe(
             Html(content).into_response()
         }
         Err(report) => {
-            let report_html = ok_status!(report_to_html(&report, entry.file_name(), &content));
+            let report_html = ok_status!(report_to_html(&report, entry.file_name(), content));
 
             let content = mj_ok!(tmpl.render(context! {
                 name => entry.name(),
@@ -316,7 +340,7 @@ impl AppState {
     fn checker(
         &self,
         relative_to: Option<&Utf8Path>,
-    ) -> Option<cooklang::analysis::RecipeRefCheck> {
+    ) -> Option<cooklang::analysis::RecipeRefCheck<'_>> {
         if self.config.recipe_ref_check {
             let relative_to =
                 relative_to.map(|r| r.parent().expect("no parent for recipe entry").to_owned());
@@ -336,7 +360,7 @@ impl AppState {
         }
     }
 
-    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
+    pub(crate) fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions<'_> {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
             metadata_validator: Some(Box::new(metadata_validator)),
@@ -9,16 +9,16 @@ use minijinja::context;
 use serde::Deserialize;
 
 use crate::{
-    cmd::serve::{locale::UserLocale, S},
-    util::{is_valid_tag, meta_name},
+    cmd::serve::{async_index::SortBy, locale::UserLocale, S},
+    util::is_valid_tag,
 };
 
-use super::{mj_ok, recipe_entry_context, Searcher};
+use super::{mj_ok, recipe_entry_context, Compare, Searcher};
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
     #[serde(default, deserialize_with = "empty_string_as_none")]
-    q: Option<String>,
+    pub q: Option<String>,
 }
 
 fn empty_string_as_none<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
@@ -36,40 +36,48 @@ where
     }
 }
 
+/// Recipes in each page of the results
+const PAGE_SIZE: usize = 12;
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default)]
+    sort: SortBy,
+}
+
 pub async fn search(
     headers: HeaderMap,
     State(state): State<S>,
     Query(query): Query<SearchQuery>,
+    Query(page): Query<PageQuery>,
     UserLocale(t): UserLocale,
 ) -> Response {
     let srch = Searcher::from(query);
 
-    let recipes = state
+    let (recipes, total) = state
         .recipe_index
         .search(
             |entry, tokens| match tokens {
-                Some(t) => {
-                    let name = if let Some(meta) = t.metadata.as_ref() {
-                        meta_name(meta).unwrap_or(entry.name())
-                    } else {
-                        entry.name()
-                    };
-                    srch.matches_recipe(name, t)
-                }
+                Some(t) => srch.matches_recipe(entry, t, &state),
                 None => false,
             },
             |entry, tokens| recipe_entry_context(entry, &state, tokens),
-            0,
-            12,
+            page.sort,
+            page.offset,
+            PAGE_SIZE,
         )
         .await;
+    let next_offset = page.offset + recipes.len();
 
-    let is_htmx_search = headers.get("HX-Trigger").is_some_and(|v| v == "search");
+    let trigger = headers.get("HX-Trigger").and_then(|v| v.to_str().ok());
+    let is_htmx_search = matches!(trigger, Some("search" | "search-sort"));
 
-    let template = if is_htmx_search {
-        "components/recipe_grid.html"
-    } else {
-        "search.html"
+    let template = match trigger {
+        Some("search-more") => "components/search_more.html",
+        _ if is_htmx_search => "components/recipe_grid.html",
+        _ => "search.html",
     };
 
     let tmpl = mj_ok!(state.templates.get_template(template));
@@ -77,6 +85,9 @@ pub async fn search(
         t,
         recipes,
         search_query => srch.to_query(),
+        sort => page.sort,
+        total,
+        next_offset => (next_offset < total).then_some(next_offset),
         is_htmx_search,
     });
     let content = mj_ok!(res);
@@ -106,21 +117,6 @@ fn error_correct_query(query: &str) -> String {
     working_string
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_error_correct_query() {
-        assert_eq!(error_correct_query("a b c"), "a b c");
-        assert_eq!(error_correct_query("a | c"), "a | c");
-        assert_eq!(error_correct_query("(b c"), "(b c)");
-        assert_eq!(error_correct_query("(a b)"), "(a b)");
-        assert_eq!(error_correct_query("a | (b | c)"), "a | (b | c)");
-        assert_eq!(error_correct_query("b) c"), "(b) c");
-    }
-}
-
 fn parse_disjunct_chunks(query: &str) -> Vec<&str> {
     let mut depth = 0;
     let mut from = 0;
@@ -207,9 +203,27 @@ impl From<SearchQuery> for Searcher {
                         None
                     }
                 } else if let Some(ingredient) = part.strip_prefix("ingredient:") {
-                    Some(Searcher::Ingredient(ingredient.to_owned()))
+                    non_empty(ingredient).map(|i| Searcher::Ingredient(i.to_owned()))
                 } else if let Some(cookware) = part.strip_prefix("cookware:") {
-                    Some(Searcher::Cookware(cookware.to_owned()))
+                    non_empty(cookware).map(|c| Searcher::Cookware(c.to_owned()))
+                } else if let Some(time) = part.strip_prefix("time:") {
+                    parse_compare(time)
+                        .and_then(|(cmp, t)| Some(Searcher::Time(cmp, parse_minutes(t)?)))
+                } else if let Some(servings) = part.strip_prefix("servings:") {
+                    parse_compare(servings)
+                        .and_then(|(cmp, n)| Some(Searcher::Servings(cmp, n.parse().ok()?)))
+                } else if let Some(author) = part.strip_prefix("author:") {
+                    non_empty(author).map(|a| Searcher::Author(a.to_lowercase()))
+                } else if let Some(source) = part.strip_prefix("source:") {
+                    non_empty(source).map(|s| Searcher::Source(s.to_lowercase()))
+                } else if let Some(folder) = part.strip_prefix("folder:") {
+                    non_empty(folder.trim_matches('/')).map(|f| Searcher::Folder(f.to_lowercase()))
+                } else if let Some(has) = part.strip_prefix("has:") {
+                    match has {
+                        "image" => Some(Searcher::HasImage),
+                        "error" => Some(Searcher::HasError),
+                        _ => None,
+                    }
                 } else {
                     Some(Searcher::NamePart(part.to_owned()))
                 }
@@ -244,6 +258,63 @@ impl From<SearchQuery> for Searcher {
     }
 }
 
+/// Filters without a value are ignored, they would match everything
+fn non_empty(value: &str) -> Option<&str> {
+    let value = value.trim();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Splits the comparison operator, `=` if there is none
+fn parse_compare(s: &str) -> Option<(Compare, &str)> {
+    let ops = [
+        ("<=", Compare::Le),
+        (">=", Compare::Ge),
+        ("<", Compare::Lt),
+        (">", Compare::Gt),
+        ("=", Compare::Eq),
+    ];
+    let (cmp, rest) = ops
+        .iter()
+        .find_map(|(op, cmp)| s.strip_prefix(op).map(|rest| (*cmp, rest)))
+        .unwrap_or((Compare::Eq, s));
+    (!rest.is_empty()).then_some((cmp, rest))
+}
+
+/// Minutes in a duration like `1h30m`, `45min` or `20`
+fn parse_minutes(s: &str) -> Option<u32> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut total = 0u32;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let n: u32 = rest[..end].parse().ok()?;
+        rest = &rest[end..];
+        let end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..end] {
+            "" | "m" | "min" => 1,
+            "h" => 60,
+            _ => return None,
+        };
+        rest = &rest[end..];
+        total = total.checked_add(n.checked_mul(factor)?)?;
+    }
+    Some(total)
+}
+
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
 impl Searcher {
     fn to_query(&self) -> String {
         match self {
@@ -274,6 +345,53 @@ impl Searcher {
                 format!("ingredient:{ingredient}").replace(' ', "+")
             }
             Searcher::Cookware(cookware) => format!("cookware:{cookware}").replace(' ', "+"),
+            Searcher::Time(cmp, minutes) => {
+                format!("time:{}{}", cmp.as_str(), format_minutes(*minutes))
+            }
+            Searcher::Servings(cmp, n) => format!("servings:{}{n}", cmp.as_str()),
+            Searcher::Author(author) => format!("author:{author}").replace(' ', "+"),
+            Searcher::Source(source) => format!("source:{source}").replace(' ', "+"),
+            Searcher::Folder(folder) => format!("folder:{folder}").replace(' ', "+"),
+            Searcher::HasImage => "has:image".to_string(),
+            Searcher::HasError => "has:error".to_string(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_correct_query() {
+        assert_eq!(error_correct_query("a b c"), "a b c");
+        assert_eq!(error_correct_query("a | c"), "a | c");
+        assert_eq!(error_correct_query("(b c"), "(b c)");
+        assert_eq!(error_correct_query("(a b)"), "(a b)");
+        assert_eq!(error_correct_query("a | (b | c)"), "a | (b | c)");
+        assert_eq!(error_correct_query("b) c"), "(b) c");
+    }
+
+    #[test]
+    fn test_metadata_terms() {
+        let query = |q: &str| {
+            Searcher::from(SearchQuery {
+                q: Some(q.to_string()),
+            })
+            .to_query()
+        };
+        let q = "time:<30m servings:>=4 author:rachel+smith source:my+book folder:desserts/cakes has:image !has:error";
+        assert_eq!(query(q), q);
+        assert_eq!(
+            query(&error_correct_query("(time:1h30 | time:>=2h")),
+            "time:1h30m | time:>=2h"
+        );
+        assert_eq!(query("time:=90min servings:2"), "time:1h30m servings:2");
+        assert_eq!(query("time:<soon servings:many has:nothing"), "");
+        assert_eq!(query("source: author:+ folder:/ ingredient: cookware:"), "");
+        assert_eq!(
+            query("author:Rachel folder:/Desserts/"),
+            "author:rachel folder:desserts"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use cooklang::{ingredient_list::IngredientList, quantity::GroupedQuantity};
+use minijinja::{context, Value};
+use serde::Deserialize;
+use tokio::task::block_in_place;
+
+use crate::cmd::serve::{
+    locale::UserLocale,
+    shopping_list::{add_to_list, parse_aisle, read_aisle, AddError, ListRecipe},
+    AppState, S,
+};
+
+use super::{check_path, encode_path, mj_ok, ok_status};
+
+pub async fn shopping_list(State(state): State<S>, UserLocale(t): UserLocale) -> Response {
+    let saved = block_in_place(|| state.shopping_list.load());
+
+    let mut list = IngredientList::new();
+    let mut recipes = Vec::with_capacity(saved.recipes.len());
+    for item in &saved.recipes {
+        let error = match add_to_list(&state, &mut list, item).await {
+            Ok(()) => None,
+            Err(AddError::Invalid) => Some("list.hasErrors"),
+            Err(_) => Some("list.notFound"),
+        };
+        let name = item.path.rsplit('/').next().unwrap_or(&item.path);
+        let path = encode_path(&item.path);
+        let href = match item.scale {
+            Some(scale) => format!("/r/{path}?scale={scale}"),
+            None => format!("/r/{path}"),
+        };
+        recipes.push(context! {
+            name,
+            href,
+            scale => item.scale,
+            remove_href => format!("/list/remove/{}", item.path),
+            error,
+        });
+    }
+
+    let aisle_text = match block_in_place(|| read_aisle(&state)) {
+        Ok(text) => text,
+        Err(err) => {
+            tracing::error!("{err:#}");
+            String::new()
+        }
+    };
+    let aisle = parse_aisle(&state, &aisle_text).unwrap_or_else(|err| {
+        tracing::error!("{err:#}");
+        Default::default()
+    });
+
+    let items = |list: IngredientList| {
+        list.into_iter()
+            .map(|(name, qty)| item_context(&name, &qty, saved.checked.contains(&name)))
+            .collect::<Vec<_>>()
+    };
+    let mut categories = Vec::new();
+    if aisle.categories.is_empty() {
+        if !list.is_empty() {
+            categories.push(context! { name => (), items => items(list) });
+        }
+    } else {
+        let categorized = list.categorize(&aisle);
+        for (name, list) in categorized.categories {
+            categories.push(context! { name, items => items(list) });
+        }
+        if !categorized.other.is_empty() {
+            categories.push(context! { name => (), items => items(categorized.other) });
+        }
+    }
+
+    let tmpl = mj_ok!(state.templates.get_template("shopping_list.html"));
+    let html = mj_ok!(tmpl.render(context! {
+        t,
+        recipes,
+        categories,
+        has_categories => !aisle.categories.is_empty(),
+    }));
+    Html(html).into_response()
+}
+
+fn item_context(name: &str, qty: &GroupedQuantity, checked: bool) -> Value {
+    context! {
+        name,
+        quantities => qty.iter().map(|q| context! {
+            value => q.value,
+            unit => q.unit_text(),
+        }).collect::<Value>(),
+        checked,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AddForm {
+    /// Servings, none for the default
+    scale: Option<u32>,
+}
+
+/// Adds a recipe to the list, or changes its servings if it's already there
+pub async fn list_add(
+    State(state): State<S>,
+    Path(path): Path<String>,
+    UserLocale(t): UserLocale,
+    Form(form): Form<AddForm>,
+) -> Response {
+    let toast_html = |text_key: &str, color: &str| {
+        let tmpl = state.templates.get_template("components/oob_toast.html")?;
+        let html = tmpl.render(context! {
+            t,
+            text_key,
+            color,
+        })?;
+        Ok::<_, minijinja::Error>(Html(html))
+    };
+
+    let item = ListRecipe {
+        path,
+        scale: form.scale,
+    };
+    // also checks the recipe is valid
+    let mut added = IngredientList::new();
+    if let Err(err) = add_to_list(&state, &mut added, &item).await {
+        let status = match err {
+            AddError::InvalidPath(status) => status,
+            AddError::NotFound(_) => StatusCode::NOT_FOUND,
+            AddError::Invalid => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        return (status, mj_ok!(toast_html("list.error", "red"))).into_response();
+    }
+
+    let res = block_in_place(|| {
+        state.shopping_list.update(|list| {
+            match list.recipes.iter_mut().find(|r| r.path == item.path) {
+                Some(r) => r.scale = item.scale,
+                None => list.recipes.push(item),
+            }
+            // the quantity to buy changed
+            for (name, _) in added.iter() {
+                list.checked.remove(name);
+            }
+        })
+    });
+    if let Err(err) = res {
+        tracing::error!("{err:#}");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            mj_ok!(toast_html("list.error", "red")),
+        )
+            .into_response();
+    }
+    mj_ok!(toast_html("list.added", "green")).into_response()
+}
+
+pub async fn list_remove(State(state): State<S>, Path(path): Path<String>) -> Response {
+    if let Err(e) = check_path(&state, &path) {
+        return e.into_response();
+    }
+    let mut remaining = block_in_place(|| state.shopping_list.load()).recipes;
+    remaining.retain(|r| r.path != path);
+    let names = merged_names(&state, &remaining).await;
+
+    ok_status!(block_in_place(|| state.shopping_list.update(|list| {
+        list.recipes.retain(|r| r.path != path);
+        // nothing else needs them
+        list.checked.retain(|name| names.contains(name));
+    })));
+    Redirect::to("/list").into_response()
+}
+
+/// Ingredients in the list for some recipes
+async fn merged_names(state: &AppState, recipes: &[ListRecipe]) -> HashSet<String> {
+    let mut list = IngredientList::new();
+    for item in recipes {
+        // broken recipes don't have any
+        let _ = add_to_list(state, &mut list, item).await;
+    }
+    list.into_iter().map(|(name, _)| name).collect()
+}
+
+pub async fn list_clear(State(state): State<S>) -> Response {
+    ok_status!(block_in_place(|| state.shopping_list.update(|list| {
+        list.recipes.clear();
+        list.checked.clear();
+    })));
+    Redirect::to("/list").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct CheckForm {
+    name: String,
+    /// Only sent when ticked
+    checked: Option<String>,
+}
+
+/// Ticks off an ingredient, or not
+pub async fn list_check(State(state): State<S>, Form(form): Form<CheckForm>) -> Response {
+    ok_status!(block_in_place(|| state.shopping_list.update(|list| {
+        if form.checked.is_some() {
+            list.checked.insert(form.name);
+        } else {
+            list.checked.remove(&form.name);
+        }
+    })));
+    Redirect::to("/list").into_response()
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::{context, Value};
+
+use crate::cmd::{
+    serve::{async_index::SortBy, locale::UserLocale, S},
+    stats::Stats,
+};
+
+use super::{mj_ok, ok_status};
+
+pub async fn stats(UserLocale(t): UserLocale, State(state): State<S>) -> Response {
+    let tmpl = mj_ok!(state.templates.get_template("stats.html"));
+
+    let (recipes, _) = state
+        .recipe_index
+        .search(
+            |_, _| true,
+            |entry, data| (entry, data.cloned()),
+            SortBy::Path,
+            0,
+            usize::MAX,
+        )
+        .await;
+    let state2 = Arc::clone(&state);
+    // checking the images reads the dirs
+    let stats = ok_status!(
+        tokio::task::spawn_blocking(move || {
+            let recipes = recipes.iter().map(|(entry, data)| (entry, data.as_ref()));
+            Stats::collect(recipes, &state2.base_path, &state2.aliases, 10)
+        })
+        .await
+    );
+
+    let res = tmpl.render(context! { t, stats => Value::from_serialize(&stats) });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::{context, Value};
+use serde::Deserialize;
+
+use crate::cmd::{
+    serve::{async_index::SortBy, locale::UserLocale, S},
+    suggest::Pantry,
+};
+
+use super::{mj_ok, ok_status, recipe_entry_context};
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default = "default_missing")]
+    missing: usize,
+}
+
+fn default_missing() -> usize {
+    2
+}
+
+pub async fn suggest(
+    UserLocale(t): UserLocale,
+    State(state): State<S>,
+    Query(query): Query<SuggestQuery>,
+) -> Response {
+    let tmpl = mj_ok!(state.templates.get_template("suggest.html"));
+
+    // read every time, the pantry changes more than the recipes
+    let pantry = ok_status!(Pantry::load(&state.config, &state.base_path));
+    let Some(pantry) = pantry else {
+        let res = tmpl.render(context! { t, no_pantry => true });
+        return Html(mj_ok!(res)).into_response();
+    };
+
+    let (mut recipes, _) = state
+        .recipe_index
+        .search(
+            |_, data| {
+                data.is_some_and(|d| {
+                    if d.required_ingredients.is_empty() {
+                        return false;
+                    }
+                    let s = pantry.suggestion(
+                        &d.required_ingredients,
+                        &d.required_cookware,
+                        &state.aliases,
+                    );
+                    s.missing.len() + s.missing_cookware.len() <= query.missing
+                })
+            },
+            |entry, data| {
+                let data = data.expect("filtered recipe without data");
+                let s = pantry.suggestion(
+                    &data.required_ingredients,
+                    &data.required_cookware,
+                    &state.aliases,
+                );
+                let recipe = recipe_entry_context(entry, &state, Some(data));
+                (s, recipe)
+            },
+            SortBy::Path,
+            0,
+            usize::MAX,
+        )
+        .await;
+    recipes.sort_by_cached_key(|(s, _)| s.sort_key());
+
+    let (ready, almost): (Vec<_>, Vec<_>) = recipes
+        .into_iter()
+        .partition(|(s, _)| s.missing.is_empty() && s.missing_cookware.is_empty());
+    let to_value = |v: Vec<_>| {
+        Value::from_iter(
+            v.into_iter()
+                .map(|(s, recipe)| context! { recipe, ..Value::from_serialize(&s) }),
+        )
+    };
+    let (ready, almost) = (to_value(ready), to_value(almost));
+
+    let res = tmpl.render(context! { t, ready, almost, missing => query.missing });
+    let content = mj_ok!(res);
+    Html(content).into_response()
+}
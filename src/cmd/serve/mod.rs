@@ -1,28 +1,34 @@
-mod async_index;
+mod api;
+pub(crate) mod async_index;
+mod auth;
 mod handlers;
 mod locale;
+mod shopping_list;
+mod thumbnails;
+mod tls;
 
 use self::{
     async_index::{AsyncFsIndex, Update},
     locale::{make_locale_store, LocaleStore},
 };
-use crate::Context;
+use crate::{config::resolve_path, Context};
 use anyhow::{bail, Context as _, Result};
 use axum::{
-    extract::Request,
+    extract::{DefaultBodyLimit, Request, State},
     http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, StatusCode},
     middleware::{self, Next},
     response::Response,
     routing::{get, post},
     Router,
 };
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::Args;
 use cooklang::CooklangParser;
 use minijinja::{context, Environment, Value};
 use rust_embed::RustEmbed;
 use std::{
     collections::HashMap,
+    io::IsTerminal,
     net::SocketAddr,
     sync::{atomic::AtomicI32, Arc},
 };
@@ -43,15 +49,91 @@ pub struct ServeArgs {
     /// Open browser on start
     #[arg(long, conflicts_with = "host", default_value_t = false)]
     open: bool,
+
+    /// PEM certificate to serve HTTPS, overrides `serve.tls_cert`
+    #[arg(long, requires = "tls_key", value_name = "PEM")]
+    tls_cert: Option<Utf8PathBuf>,
+
+    /// PEM private key of the certificate, overrides `serve.tls_key`
+    #[arg(long, requires = "tls_cert", value_name = "PEM")]
+    tls_key: Option<Utf8PathBuf>,
+
+    /// Also listen for plain HTTP in this port and redirect it to HTTPS
+    #[arg(long, value_name = "PORT")]
+    http_port: Option<u16>,
+
+    /// Create a self-signed certificate in `.cooklang/tls`
+    ///
+    /// It's valid for localhost and this machine IP, add other host names or
+    /// IPs it will be accessed with.
+    #[arg(long, exclusive = true, num_args = 0.., value_name = "NAME")]
+    generate_cert: Option<Vec<String>>,
+
+    /// Ask for a password and print its hash for `[auth.users]` in the config
+    #[arg(long, exclusive = true)]
+    hash_password: bool,
 }
 
 #[tokio::main]
 pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
+    if args.hash_password {
+        let password = if std::io::stdin().is_terminal() {
+            inquire::Password::new("Password:").prompt()?
+        } else {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        };
+        println!("{}", auth::hash_password(&password)?);
+        return Ok(());
+    }
+
     if !ctx.is_collection {
         bail!("`serve` needs to run inside a collection");
     }
 
-    let state = build_state(ctx).context("failed to build web server")?;
+    if let Some(names) = &args.generate_cert {
+        let (cert, key) = tls::generate_cert(&ctx.base_path, names)?;
+        println!("Certificate written to '{cert}' and key to '{key}'.");
+        println!("Add them to the config to use them:\n");
+        println!(
+            "[serve]\ntls_cert = \"{}\"\ntls_key = \"{}\"",
+            tls::CERT_FILE,
+            tls::KEY_FILE
+        );
+        return Ok(());
+    }
+
+    let serve_config = &ctx.config.serve;
+    let resolve = |p: &std::path::Path| resolve_path(&ctx.base_path, p);
+    let tls_files = match (
+        args.tls_cert
+            .clone()
+            .or(serve_config.tls_cert.as_deref().map(resolve)),
+        args.tls_key
+            .clone()
+            .or(serve_config.tls_key.as_deref().map(resolve)),
+    ) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => bail!("HTTPS needs both `tls_cert` and `tls_key`"),
+    };
+    let tls_config = match &tls_files {
+        Some((cert, key)) => Some(tls::rustls_config(cert, key).await?),
+        None => None,
+    };
+    let http_port = args.http_port.or(serve_config.http_port);
+    if http_port.is_some() && tls_config.is_none() {
+        bail!("The HTTP port redirects to HTTPS, it needs `tls_cert` and `tls_key`");
+    }
+    if http_port == Some(args.port) {
+        bail!("The HTTP port must be different from the HTTPS one");
+    }
+
+    let state = build_state(ctx, tls_config.is_some()).context("failed to build web server")?;
+    if args.host && !state.auth.is_enabled() {
+        tracing::warn!("Anyone in the network can see and edit the recipes, set up `[auth]` in the config to prevent it");
+    }
     let app = make_router(state);
 
     let addr = if args.host {
@@ -60,10 +142,28 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
         SocketAddr::from(([127, 0, 0, 1], args.port))
     };
 
+    // bound before serving, so a busy port stops the server from starting
+    let redirect_listener = match http_port {
+        Some(http_port) => {
+            let http_addr = SocketAddr::new(addr.ip(), http_port);
+            let listener = tokio::net::TcpListener::bind(&http_addr)
+                .await
+                .with_context(|| format!("failed to listen on {http_addr}"))?;
+            info!("Redirecting {http_addr} to HTTPS");
+            Some(listener)
+        }
+        None => None,
+    };
+
     info!("Listening on {addr}");
 
     if args.open {
-        let url = format!("http://{}:{}", addr.ip(), addr.port());
+        let scheme = if tls_config.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let url = format!("{scheme}://{}:{}", addr.ip(), addr.port());
         info!("Serving web UI on {url}");
         tokio::task::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
@@ -73,9 +173,24 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
         });
     }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
     let app_service = app.into_make_service_with_connect_info::<SocketAddr>();
-    axum::serve(listener, app_service).await.unwrap();
+    if let Some(config) = tls_config {
+        let https = async {
+            axum_server::bind_rustls(addr, config)
+                .serve(app_service)
+                .await
+                .with_context(|| format!("failed to serve on {addr}"))
+        };
+        match redirect_listener {
+            Some(listener) => {
+                tokio::try_join!(https, tls::redirect_http(listener, addr.port()))?;
+            }
+            None => https.await?,
+        }
+    } else {
+        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+        axum::serve(listener, app_service).await.unwrap();
+    }
 
     info!("Server stopped");
 
@@ -84,23 +199,61 @@ pub async fn run(ctx: Context, args: ServeArgs) -> Result<()> {
 
 #[tracing::instrument(level = "debug", skip_all)]
 fn make_router(state: Arc<AppState>) -> Router {
+    // change the recipes or the shopping list, or run commands
+    let write_routes = Router::new()
+        .route("/open_editor/*path", get(handlers::open_editor))
+        .route("/edit/*path", get(handlers::edit).post(handlers::edit_save))
+        .route("/edit_preview/*path", post(handlers::edit_preview))
+        .route(
+            "/new",
+            get(handlers::new_recipe).post(handlers::create_recipe),
+        )
+        .route(
+            "/upload_image/*path",
+            post(handlers::upload_image)
+                .layer(DefaultBodyLimit::max(handlers::new_recipe::MAX_UPLOAD_SIZE)),
+        )
+        .route("/list/add/*path", post(handlers::list_add))
+        .route("/list/remove/*path", post(handlers::list_remove))
+        .route("/list/check", post(handlers::list_check))
+        .route("/list/clear", post(handlers::list_clear))
+        .route_layer(middleware::from_fn(auth::require_write));
+
     Router::new()
         .route("/", get(handlers::index))
         .route("/d/*path", get(handlers::index))
         .route("/search", get(handlers::search))
         .route("/about", get(handlers::about))
+        .route("/stats", get(handlers::stats))
+        .route("/suggest", get(handlers::suggest))
         .route("/r/*path", get(handlers::recipe))
+        .route("/cook/*path", get(handlers::cook))
+        .route("/img/:width/*path", get(handlers::image))
         .route("/updates", get(handlers::sse_updates))
-        .route("/open_editor/*path", get(handlers::open_editor))
+        .merge(write_routes)
+        .route("/list", get(handlers::shopping_list))
         .route("/convert_modal", post(handlers::convert_popover))
+        .nest("/api/v1", api::router())
         .nest_service(
             "/src",
             ServiceBuilder::new()
-                .layer(middleware::from_fn(filter_files))
+                .layer(middleware::from_fn_with_state(
+                    Arc::clone(&state),
+                    filter_files,
+                ))
                 .layer(middleware::from_fn(cook_mime_type))
                 .service(tower_http::services::ServeDir::new(&state.base_path)),
         )
+        // everything above needs to log in, but not the login page or the
+        // static files in the fallback
+        .route_layer(middleware::from_fn_with_state(
+            Arc::clone(&state),
+            auth::authenticate,
+        ))
+        .route("/login", get(handlers::login).post(handlers::login_submit))
+        .route("/logout", post(handlers::logout))
         .fallback(handlers::static_file)
+        .layer(middleware::from_fn(auth::check_origin))
         .with_state(state)
 }
 
@@ -112,28 +265,41 @@ pub struct AppState {
     recipe_index: AsyncFsIndex,
     updates_stream: broadcast::Receiver<Update>,
     config: crate::config::Config,
+    aliases: crate::aliases::Aliases,
+    symlinks: cooklang_fs::Symlinks,
+    thumbnails: thumbnails::Thumbnails,
     editor_command: Option<Vec<String>>,
     editor_count: AtomicI32,
+    /// For new recipes
+    author: Option<String>,
+    shopping_list: shopping_list::ListStore,
+    auth: auth::Auth,
 }
 
 type S = Arc<AppState>;
 
 #[tracing::instrument(level = "debug", skip_all)]
-fn build_state(ctx: Context) -> Result<S> {
+fn build_state(ctx: Context, https: bool) -> Result<S> {
     ctx.parser()?;
+    let aliases = ctx.aliases()?.clone();
     let Context {
         parser,
         recipe_index,
         base_path,
         config,
         chef_config,
+        symlinks,
         ..
     } = ctx;
     let parser = Arc::new(parser.into_inner().unwrap());
     let complete_index = recipe_index
         .index_all()
         .context("failed to index the recipes")?;
-    let (recipe_index, updates) = AsyncFsIndex::new(complete_index, Arc::clone(&parser));
+    let cache = crate::cache::RecipeCache::load(&base_path, &config);
+    let thumbnails = thumbnails::Thumbnails::new(&base_path);
+    let shopping_list = shopping_list::ListStore::new(&base_path);
+    let auth = auth::Auth::new(config.auth.clone(), https);
+    let (recipe_index, updates) = AsyncFsIndex::new(complete_index, Arc::clone(&parser), cache);
 
     let locales = make_locale_store();
     let templates = make_template_env(&locales);
@@ -146,8 +312,14 @@ fn build_state(ctx: Context) -> Result<S> {
         recipe_index,
         updates_stream: updates,
         config,
+        aliases,
+        thumbnails,
+        symlinks,
         editor_command: chef_config.editor().ok(),
         editor_count: 0.into(),
+        author: chef_config.author,
+        shopping_list,
+        auth,
     }))
 }
 
@@ -260,6 +432,8 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
         },
     );
 
+    env.add_filter("url_path", |v: &str| handlers::encode_path(v));
+
     env.add_filter("zeroless_float", |v: f64| {
         if v.fract() == 0.0 {
             Value::from(v as i128)
@@ -272,9 +446,23 @@ fn make_template_env(locales: &LocaleStore) -> Environment<'static> {
 }
 
 /// filters static files to only expose images and cook files
-async fn filter_files(req: Request, next: Next) -> impl axum::response::IntoResponse {
-    let path = req.uri().path();
+async fn filter_files(
+    State(state): State<S>,
+    req: Request,
+    next: Next,
+) -> impl axum::response::IntoResponse {
+    let path = percent_encoding::percent_decode_str(req.uri().path())
+        .decode_utf8()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
     let (_, ext) = path.rsplit_once('.').ok_or(StatusCode::NOT_FOUND)?;
+    let path = Utf8Path::new(path.trim_start_matches('/'));
+    if cooklang_fs::is_ignored(&state.base_path, path)
+        || !state
+            .symlinks
+            .is_contained(&state.base_path, &state.base_path.join(path))
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
     if ext == "cook" || cooklang_fs::IMAGE_EXTENSIONS.contains(&ext) {
         Ok(next.run(req).await)
     } else {
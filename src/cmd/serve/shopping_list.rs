@@ -0,0 +1,137 @@
+//! Shopping list of the web UI
+//!
+//! Only the recipes and the ticked off ingredients are stored, in
+//! `.cooklang/shopping_list.json`, so every device on the network sees the
+//! same list. The ingredients are merged again each time it is shown, so
+//! changes to the recipes are picked up.
+
+use std::{collections::BTreeSet, sync::Mutex};
+
+use anyhow::{anyhow, Context as _, Result};
+use axum::http::StatusCode;
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{aisle::AisleConf, ingredient_list::IngredientList};
+use serde::{Deserialize, Serialize};
+use tokio::task::block_in_place;
+
+use crate::{cmd::shopping_list::add_recipe, util::write_atomic, COOK_DIR};
+
+use super::{handlers::check_path, AppState};
+
+const LIST_FILE: &str = "shopping_list.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ShoppingList {
+    pub recipes: Vec<ListRecipe>,
+    /// Names of the ingredients already bought
+    #[serde(default)]
+    pub checked: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListRecipe {
+    /// Relative to the collection, without extension
+    pub path: String,
+    /// Servings, `None` for the default
+    pub scale: Option<u32>,
+}
+
+pub struct ListStore {
+    file: Utf8PathBuf,
+    /// Serializes the updates, the file is the state
+    lock: Mutex<()>,
+}
+
+impl ListStore {
+    pub fn new(base_path: &Utf8Path) -> Self {
+        Self {
+            file: base_path.join(COOK_DIR).join(LIST_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Reads the list, empty if there is none yet
+    pub fn load(&self) -> ShoppingList {
+        let text = match std::fs::read(&self.file) {
+            Ok(text) => text,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Could not read shopping list: {err}");
+                }
+                return ShoppingList::default();
+            }
+        };
+        serde_json::from_slice(&text).unwrap_or_else(|err| {
+            tracing::warn!("Ignoring broken shopping list file: {err}");
+            ShoppingList::default()
+        })
+    }
+
+    /// Changes the list and writes it back
+    pub fn update<R>(&self, f: impl FnOnce(&mut ShoppingList) -> R) -> Result<R> {
+        let _guard = self.lock.lock().unwrap();
+        let mut list = self.load();
+        let r = f(&mut list);
+        let json = serde_json::to_vec_pretty(&list)?;
+        write_atomic(&self.file, &json).context("Failed to write shopping list")?;
+        Ok(r)
+    }
+}
+
+/// Why a recipe can't be added to an [`IngredientList`]
+pub enum AddError {
+    InvalidPath(StatusCode),
+    NotFound(String),
+    /// The recipe has errors
+    Invalid,
+}
+
+/// Parses, scales and adds the ingredients of a recipe to the list
+pub async fn add_to_list(
+    state: &AppState,
+    list: &mut IngredientList,
+    item: &ListRecipe,
+) -> Result<(), AddError> {
+    check_path(state, &item.path).map_err(AddError::InvalidPath)?;
+    let entry = state
+        .recipe_index
+        .get(&item.path)
+        .await
+        .map_err(|e| AddError::NotFound(e.to_string()))?;
+    let content = tokio::fs::read_to_string(entry.path())
+        .await
+        .map_err(|e| AddError::NotFound(e.to_string()))?;
+    let recipe = block_in_place(|| {
+        state
+            .parser
+            .parse_with_options(&content, state.parse_options(Some(entry.path())))
+            .into_output()
+    })
+    .ok_or(AddError::Invalid)?;
+
+    let converter = state.parser.converter();
+    let scaled = match item.scale {
+        Some(servings) => recipe.scale(servings, converter),
+        None => recipe.default_scale(),
+    };
+    add_recipe(list, &scaled, converter, &state.aliases);
+    Ok(())
+}
+
+/// Reads the aisle file of the collection, empty if there is none
+///
+/// It's read every time, like the pantry.
+pub fn read_aisle(state: &AppState) -> Result<String> {
+    match state.config.aisle(&state.base_path) {
+        Some(path) => std::fs::read_to_string(&path).context("Failed to read aisle file"),
+        None => Ok(String::new()),
+    }
+}
+
+/// Parses the aisle file with the aliases of the collection
+pub fn parse_aisle<'a>(state: &'a AppState, text: &'a str) -> Result<AisleConf<'a>> {
+    let mut aisle =
+        cooklang::aisle::parse(text).map_err(|e| anyhow!("Error parsing aisle file: {e}"))?;
+    state.aliases.extend_aisle(&mut aisle);
+    Ok(aisle)
+}
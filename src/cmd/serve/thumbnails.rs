@@ -0,0 +1,158 @@
+//! Resized recipe images for the web UI
+//!
+//! Photos from a phone can be several MB, so the UI asks for one of a few
+//! widths. They are generated on the first request and kept in
+//! `.cooklang/cache/images`. A thumbnail older than its image is generated
+//! again.
+//!
+//! Images with transparency are encoded as lossless WebP, the rest as JPEG.
+
+use std::io::Cursor;
+
+use anyhow::{Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    DynamicImage, ImageDecoder, ImageReader,
+};
+
+use crate::{cache::CACHE_DIR, util::write_atomic, COOK_DIR};
+
+/// Widths of the thumbnails, in pixels
+pub const WIDTHS: &[u32] = &[240, 480, 960, 1600];
+/// Width for browsers without `srcset` support
+pub const DEFAULT_WIDTH: u32 = 960;
+const IMAGES_DIR: &str = "images";
+const JPEG_QUALITY: u8 = 80;
+/// Extensions of the thumbnails, in the order they are looked up
+const FORMATS: &[&str] = &["jpg", "webp"];
+
+/// Formats no pure Rust decoder can read
+const UNSUPPORTED_EXTENSIONS: &[&str] = &["heic"];
+
+/// Checks if thumbnails can be made from an image
+pub fn is_supported(path: &Utf8Path) -> bool {
+    !path
+        .extension()
+        .is_some_and(|ext| UNSUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+pub enum Thumbnail {
+    /// Stored in the cache
+    File(Utf8PathBuf),
+    /// Outside a collection nothing is stored
+    Memory { data: Vec<u8>, mime: &'static str },
+}
+
+pub struct Thumbnails {
+    base_path: Utf8PathBuf,
+    /// `None` when not in a collection
+    cache_dir: Option<Utf8PathBuf>,
+}
+
+impl Thumbnails {
+    pub fn new(base_path: &Utf8Path) -> Self {
+        let cook_dir = base_path.join(COOK_DIR);
+        Self {
+            base_path: base_path.to_path_buf(),
+            cache_dir: cook_dir
+                .is_dir()
+                .then(|| cook_dir.join(CACHE_DIR).join(IMAGES_DIR)),
+        }
+    }
+
+    /// Gets the thumbnail of an image, generating it if needed
+    ///
+    /// `path` is relative to the collection. This blocks, the image may be
+    /// decoded and encoded.
+    pub fn get(&self, path: &Utf8Path, width: u32) -> Result<Thumbnail> {
+        let src = self.base_path.join(path);
+        let modified = src.metadata()?.modified()?;
+
+        let Some(cache_dir) = &self.cache_dir else {
+            let (data, ext) = make_thumbnail(&src, width)?;
+            return Ok(Thumbnail::Memory {
+                data,
+                mime: mime(ext),
+            });
+        };
+
+        let cached = |ext: &str| cache_dir.join(format!("{path}.{width}.{ext}"));
+        for ext in FORMATS {
+            let file = cached(ext);
+            let fresh = file
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| t >= modified);
+            if fresh {
+                return Ok(Thumbnail::File(file));
+            }
+        }
+
+        tracing::debug!("generating {width}px thumbnail of '{path}'");
+        let (data, ext) = make_thumbnail(&src, width)?;
+        let file = cached(ext);
+        write_atomic(&file, &data)
+            .with_context(|| format!("Failed to write thumbnail '{file}'"))?;
+        // the image may have changed format
+        for other in FORMATS.iter().filter(|e| **e != ext) {
+            let _ = std::fs::remove_file(cached(other));
+        }
+        Ok(Thumbnail::File(file))
+    }
+}
+
+/// Returns the encoded image and its extension
+fn make_thumbnail(src: &Utf8Path, width: u32) -> Result<(Vec<u8>, &'static str)> {
+    let mut decoder = ImageReader::open(src)?
+        .with_guessed_format()?
+        .into_decoder()
+        .with_context(|| format!("Failed to read image '{src}'"))?;
+    let orientation = decoder.orientation()?;
+    let mut img = DynamicImage::from_decoder(decoder)
+        .with_context(|| format!("Failed to decode image '{src}'"))?;
+    // phone photos are usually rotated with exif
+    img.apply_orientation(orientation);
+
+    // never make it bigger
+    if img.width() > width {
+        img = img.thumbnail(width, u32::MAX);
+    }
+
+    let mut data = Cursor::new(Vec::new());
+    let ext = if img.color().has_alpha() {
+        let rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+        rgba.write_with_encoder(WebPEncoder::new_lossless(&mut data))?;
+        "webp"
+    } else {
+        let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+        rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY))?;
+        "jpg"
+    };
+    Ok((data.into_inner(), ext))
+}
+
+fn mime(ext: &str) -> &'static str {
+    match ext {
+        "webp" => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+/// `srcset` attribute for an image in the collection
+///
+/// `path` is relative to the collection and must be url encoded.
+pub fn srcset(path: &str) -> String {
+    WIDTHS
+        .iter()
+        .map(|w| format!("{} {w}w", url(path, *w)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Url of the thumbnail of an image in the collection
+///
+/// `path` is relative to the collection and must be url encoded.
+pub fn url(path: &str, width: u32) -> String {
+    format!("/img/{width}/{path}")
+}
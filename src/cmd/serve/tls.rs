@@ -0,0 +1,97 @@
+//! HTTPS with rustls, and a self-signed certificate for the first setup
+
+use std::{
+    io::Write,
+    net::{IpAddr, UdpSocket},
+};
+
+use anyhow::{bail, Context as _, Result};
+use axum::{
+    http::{header::HOST, HeaderMap, Uri},
+    response::Redirect,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use camino::{Utf8Path, Utf8PathBuf};
+use tokio::net::TcpListener;
+
+use crate::COOK_DIR;
+
+/// Generated certificate, relative to the `.cooklang` dir
+pub const CERT_FILE: &str = "tls/cert.pem";
+pub const KEY_FILE: &str = "tls/key.pem";
+
+/// Writes a self-signed certificate for this machine and `names`
+///
+/// Returns the paths of the certificate and the key.
+pub fn generate_cert(base_path: &Utf8Path, names: &[String]) -> Result<(Utf8PathBuf, Utf8PathBuf)> {
+    let cert_path = base_path.join(COOK_DIR).join(CERT_FILE);
+    let key_path = base_path.join(COOK_DIR).join(KEY_FILE);
+    if cert_path.exists() || key_path.exists() {
+        bail!("'{cert_path}' or '{key_path}' already exist, delete them to generate new ones");
+    }
+
+    let mut all_names = vec!["localhost".to_string(), "127.0.0.1".into(), "::1".into()];
+    all_names.extend(local_ip().map(|ip| ip.to_string()));
+    for name in names {
+        if !all_names.contains(name) {
+            all_names.push(name.clone());
+        }
+    }
+    let rcgen::CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(all_names)
+        .context("failed to generate the certificate")?;
+
+    std::fs::create_dir_all(cert_path.parent().unwrap())?;
+    std::fs::write(&cert_path, cert.pem())
+        .with_context(|| format!("failed to write '{cert_path}'"))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&key_path)
+        .and_then(|mut file| file.write_all(key_pair.serialize_pem().as_bytes()))
+        .with_context(|| format!("failed to write '{key_path}'"))?;
+
+    Ok((cert_path, key_path))
+}
+
+/// Address of this machine in the local network
+fn local_ip() -> Option<IpAddr> {
+    // connecting an UDP socket doesn't send anything, it only picks the
+    // interface
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+}
+
+pub async fn rustls_config(cert: &Utf8Path, key: &Utf8Path) -> Result<RustlsConfig> {
+    // only ring is enabled, but rustls still needs it to be chosen
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| format!("failed to load the certificate '{cert}' and key '{key}'"))
+}
+
+/// Redirects plain HTTP from `listener` to HTTPS in `https_port`
+pub async fn redirect_http(listener: TcpListener, https_port: u16) -> Result<()> {
+    let app = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        let host = headers
+            .get(HOST)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("localhost");
+        // without the port, but keep IPv6 like `[::1]`
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => host,
+        };
+        let path = uri.path_and_query().map_or("/", |p| p.as_str());
+        Redirect::temporary(&format!("https://{host}:{https_port}{path}"))
+    });
+
+    axum::serve(listener, app)
+        .await
+        .context("failed to redirect HTTP")?;
+    Ok(())
+}
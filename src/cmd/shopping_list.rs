@@ -3,13 +3,14 @@ use camino::Utf8PathBuf;
 use clap::{Args, CommandFactory, ValueEnum};
 use cooklang::{
     aisle::AisleConf,
-    ingredient_list::IngredientList,
-    quantity::{GroupedQuantity, Quantity, Value},
-    ScaledQuantity,
+    ingredient_list::{GroupedIngredient, IngredientList},
+    quantity::{GroupedQuantity, Quantity},
+    scale::ScaleOutcome,
+    Converter, ScaledQuantity, ScaledRecipe,
 };
 use serde::Serialize;
 
-use crate::{util::write_to_output, util::Input, Context};
+use crate::{aliases::Aliases, util::write_to_output, util::Input, Context};
 
 #[derive(Debug, Args)]
 pub struct ShoppingListArgs {
@@ -73,7 +74,9 @@ pub fn run(ctx: &Context, mut args: ShoppingListArgs) -> Result<()> {
             Ok(res.unwrap()) // anyhow
         })
         .transpose()?;
-    let aisle = aisle.unwrap_or_default();
+    let aliases = ctx.aliases()?;
+    let mut aisle = aisle.unwrap_or_default();
+    aliases.extend_aisle(&mut aisle);
     if aisle.categories.is_empty() {
         args.plain = true;
     }
@@ -148,12 +151,36 @@ fn extract_ingredients(entry: &str, list: &mut IngredientList, ctx: &Context) ->
         recipe.default_scale()
     };
 
-    // Add ingredients to the list
-    list.add_recipe(&recipe, converter);
-
+    add_recipe(list, &recipe, converter, ctx.aliases()?);
     Ok(())
 }
 
+/// Adds the ingredients of a recipe to the list, synonyms under the same name
+pub fn add_recipe(
+    list: &mut IngredientList,
+    recipe: &ScaledRecipe,
+    converter: &Converter,
+    aliases: &Aliases,
+) {
+    for entry in recipe.group_ingredients(converter) {
+        let GroupedIngredient {
+            ingredient,
+            quantity,
+            outcome,
+            ..
+        } = entry;
+        if !ingredient.modifiers().should_be_listed() {
+            continue;
+        }
+        if let Some(ScaleOutcome::Error(err)) = outcome {
+            tracing::error!("Error scaling ingredient: {err}");
+        }
+        let name = ingredient.display_name();
+        let name = aliases.canonical(&name).to_string();
+        list.add_ingredient(name, &quantity, converter);
+    }
+}
+
 fn grouped_qty_fmt(qty: &GroupedQuantity, row: &mut tabular::Row) {
     let content = qty
         .iter()
@@ -197,23 +224,11 @@ fn build_human_table(list: IngredientList, aisle: &AisleConf, plain: bool) -> ta
     table
 }
 
-fn build_json_value<'a>(
+pub fn build_json_value<'a>(
     list: IngredientList,
     aisle: &'a AisleConf<'a>,
     plain: bool,
 ) -> serde_json::Value {
-    #[derive(Serialize)]
-    struct Quantity {
-        value: Value,
-        unit: Option<String>,
-    }
-    impl From<cooklang::quantity::Quantity> for Quantity {
-        fn from(qty: cooklang::quantity::Quantity) -> Self {
-            let unit = qty.unit_text().map(|s| s.to_owned());
-            let value = qty.value;
-            Self { value, unit }
-        }
-    }
     #[derive(Serialize)]
     struct Ingredient {
         name: String,
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use anstream::{print, println};
+use anyhow::{bail, Result};
+use camino::Utf8Path;
+use clap::{Args, ValueEnum};
+use cooklang::metadata::RecipeTime;
+use cooklang_fs::{all_recipes_with, RecipeEntry};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    aliases::Aliases,
+    cache::{RecipeCache, RecipeData},
+    Context,
+};
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+
+    /// Pretty output format, if available
+    #[arg(long)]
+    pretty: bool,
+
+    /// Number of ingredients and cookware to show
+    #[arg(short = 'n', long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Numbers about a whole collection
+#[derive(Debug, Default, Serialize)]
+pub struct Stats {
+    pub recipes: usize,
+    /// Recipes that could not be parsed, not included in the rest
+    pub with_errors: Vec<String>,
+    pub folders: Vec<(String, usize)>,
+    pub tags: Vec<(String, usize)>,
+    pub ingredients: Vec<(String, usize)>,
+    pub cookware: Vec<(String, usize)>,
+    pub without_images: Vec<String>,
+    pub without_tags: Vec<String>,
+    pub avg_ingredients: f64,
+    pub avg_steps: f64,
+    /// Number of recipes in each [`TIME_BUCKETS`] and in the last place, the
+    /// ones without time
+    pub time: Vec<(&'static str, usize)>,
+}
+
+/// Upper limits in minutes and label of the time distribution
+const TIME_BUCKETS: &[(u32, &str)] = &[
+    (15, "< 15 min"),
+    (30, "15-30 min"),
+    (60, "30-60 min"),
+    (120, "1-2 h"),
+    (u32::MAX, "> 2 h"),
+];
+const NO_TIME: &str = "unknown";
+
+impl Stats {
+    /// Collects the stats from the recipes and their parsed data
+    ///
+    /// Recipes without data are counted as having errors. `top` limits the
+    /// number of ingredients and cookware. Synonyms in `aliases` are counted
+    /// as one ingredient.
+    pub fn collect<'a>(
+        recipes: impl IntoIterator<Item = (&'a RecipeEntry, Option<&'a RecipeData>)>,
+        base_path: &Utf8Path,
+        aliases: &Aliases,
+        top: usize,
+    ) -> Self {
+        let mut stats = Stats::default();
+        let mut folders = HashMap::<String, usize>::new();
+        let mut tags = HashMap::<String, usize>::new();
+        let mut ingredients = HashMap::<String, usize>::new();
+        let mut cookware = HashMap::<String, usize>::new();
+        let mut time = vec![0; TIME_BUCKETS.len() + 1];
+        let mut total_ingredients = 0;
+        let mut total_steps = 0;
+        let mut parsed = 0;
+
+        for (entry, data) in recipes {
+            stats.recipes += 1;
+            let rel = entry
+                .path()
+                .strip_prefix(base_path)
+                .unwrap_or(entry.path())
+                .with_extension("")
+                .into_string();
+            let folder = entry
+                .path()
+                .strip_prefix(base_path)
+                .ok()
+                .and_then(|p| p.parent())
+                .map(|p| p.as_str())
+                .filter(|p| !p.is_empty())
+                .unwrap_or(".")
+                .to_string();
+            *folders.entry(folder).or_default() += 1;
+            if entry.images().is_empty() {
+                stats.without_images.push(rel.clone());
+            }
+
+            let Some((data, metadata)) = data.and_then(|d| Some((d, d.metadata.as_ref()?))) else {
+                stats.with_errors.push(rel);
+                continue;
+            };
+            parsed += 1;
+
+            match metadata.tags() {
+                Some(t) if !t.is_empty() => {
+                    for tag in t {
+                        *tags.entry(tag.clone()).or_default() += 1;
+                    }
+                }
+                _ => stats.without_tags.push(rel),
+            }
+
+            let mut names = data
+                .ingredients
+                .iter()
+                .filter(|name| !data.references.contains(name))
+                .map(|name| aliases.canonical(name).to_lowercase())
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            names.dedup();
+            total_ingredients += names.len();
+            for name in names {
+                *ingredients.entry(name).or_default() += 1;
+            }
+            let mut names = data
+                .cookware
+                .iter()
+                .map(|name| name.to_lowercase())
+                .collect::<Vec<_>>();
+            names.sort_unstable();
+            names.dedup();
+            for name in names {
+                *cookware.entry(name).or_default() += 1;
+            }
+
+            total_steps += data.steps;
+
+            let minutes = metadata.time().cloned().map(RecipeTime::total);
+            let bucket = match minutes {
+                Some(m) => TIME_BUCKETS
+                    .iter()
+                    .position(|&(limit, _)| m < limit)
+                    .unwrap_or(TIME_BUCKETS.len() - 1),
+                None => TIME_BUCKETS.len(),
+            };
+            time[bucket] += 1;
+        }
+
+        if parsed > 0 {
+            stats.avg_ingredients = total_ingredients as f64 / parsed as f64;
+            stats.avg_steps = total_steps as f64 / parsed as f64;
+        }
+        stats.folders = sorted(folders, usize::MAX);
+        stats.folders.sort_unstable();
+        stats.tags = sorted(tags, usize::MAX);
+        stats.ingredients = sorted(ingredients, top);
+        stats.cookware = sorted(cookware, top);
+        stats.time = TIME_BUCKETS
+            .iter()
+            .map(|&(_, label)| label)
+            .chain([NO_TIME])
+            .zip(time)
+            .collect();
+        stats.with_errors.sort_unstable();
+        stats.without_images.sort_unstable();
+        stats.without_tags.sort_unstable();
+        stats
+    }
+}
+
+/// Most common first, then alphabetically
+fn sorted(counts: HashMap<String, usize>, take: usize) -> Vec<(String, usize)> {
+    let mut v = counts.into_iter().collect::<Vec<_>>();
+    v.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    v.truncate(take);
+    v
+}
+
+pub fn run(ctx: &Context, args: StatsArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`stats` needs to run inside a collection");
+    }
+
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let parser = ctx.parser()?;
+    let mut cache = RecipeCache::load(&ctx.base_path, &ctx.config);
+    cache.update(&entries, parser);
+    let data = entries
+        .iter()
+        .map(|entry| cache.get(entry, parser).ok().cloned())
+        .collect::<Vec<_>>();
+    cache.save();
+    let recipes = entries.iter().zip(data.iter().map(Option::as_ref));
+    let stats = Stats::collect(recipes, &ctx.base_path, ctx.aliases()?, args.top);
+
+    match args.format {
+        OutputFormat::Human => print_human(&stats),
+        OutputFormat::Json => {
+            let stdout = std::io::stdout().lock();
+            if args.pretty {
+                serde_json::to_writer_pretty(stdout, &stats)?;
+            } else {
+                serde_json::to_writer(stdout, &stats)?;
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn print_human(stats: &Stats) {
+    use tabular::{row, table};
+
+    let mut table = table!("{:>}  {:<}", row!("Recipes", stats.recipes));
+    table.add_row(row!("With errors", stats.with_errors.len()));
+    table.add_row(row!("Without images", stats.without_images.len()));
+    table.add_row(row!("Without tags", stats.without_tags.len()));
+    table.add_row(row!(
+        "Avg. ingredients",
+        format!("{:.1}", stats.avg_ingredients)
+    ));
+    table.add_row(row!("Avg. steps", format!("{:.1}", stats.avg_steps)));
+    print!("{table}");
+
+    let section = |title: &str, values: &[(String, usize)]| {
+        if values.is_empty() {
+            return;
+        }
+        println!();
+        println!("{}", title.bold());
+        let mut table = tabular::Table::new("  {:>}  {:<}");
+        for (name, count) in values {
+            table.add_row(row!(count, name));
+        }
+        print!("{table}");
+    };
+    section("Folders", &stats.folders);
+    section("Tags", &stats.tags);
+    section("Most used ingredients", &stats.ingredients);
+    section("Most used cookware", &stats.cookware);
+
+    println!();
+    println!("{}", "Time".bold());
+    let mut table = tabular::Table::new("  {:>}  {:<}");
+    for (label, count) in &stats.time {
+        table.add_row(row!(count, label));
+    }
+    print!("{table}");
+
+    let list = |title: &str, values: &[String]| {
+        if values.is_empty() {
+            return;
+        }
+        println!();
+        println!("{}", title.bold());
+        for v in values {
+            println!("  {v}");
+        }
+    };
+    list("Could not parse", &stats.with_errors);
+    list("Without images", &stats.without_images);
+    list("Without tags", &stats.without_tags);
+}
@@ -0,0 +1,199 @@
+use anstream::{eprintln, print};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8Path;
+use clap::Args;
+use cooklang_fs::all_recipes_with;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    aliases::Aliases,
+    config::Config,
+    util::{par_map, required_cookware, required_ingredients},
+    Context,
+};
+
+/// Category of the pantry file with the cookware
+const COOKWARE_CATEGORY: &str = "cookware";
+
+#[derive(Debug, Args)]
+pub struct SuggestArgs {
+    /// Show recipes missing up to this number of ingredients
+    #[arg(short, long, default_value_t = 2)]
+    max_missing: usize,
+
+    /// Don't check the cookware, even if the pantry has it
+    #[arg(long)]
+    ignore_cookware: bool,
+}
+
+/// What we have at home
+///
+/// The pantry file uses the same format as `aisle.conf`. A `[cookware]`
+/// category lists the cookware, the rest are ingredients.
+#[derive(Debug, Default)]
+pub struct Pantry {
+    ingredients: Vec<String>,
+    cookware: Vec<String>,
+}
+
+impl Pantry {
+    pub fn load(config: &Config, base_path: &Utf8Path) -> Result<Option<Self>> {
+        let Some(path) = config.pantry(base_path) else {
+            return Ok(None);
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read pantry file '{path}'"))?;
+        let conf = cooklang::aisle::parse(&text)
+            .with_context(|| format!("Error parsing pantry file '{path}'"))?;
+        let mut pantry = Self::default();
+        for cat in &conf.categories {
+            let names = if cat.name.eq_ignore_ascii_case(COOKWARE_CATEGORY) {
+                &mut pantry.cookware
+            } else {
+                &mut pantry.ingredients
+            };
+            names.extend(
+                cat.ingredients
+                    .iter()
+                    .flat_map(|i| &i.names)
+                    .map(|n| n.to_lowercase()),
+            );
+        }
+        Ok(Some(pantry))
+    }
+
+    fn has_ingredient(&self, name: &str, aliases: &Aliases) -> bool {
+        let name = name.to_lowercase();
+        let synonyms = aliases.synonyms(&name);
+        self.ingredients
+            .iter()
+            .any(|i| *i == name || synonyms.iter().any(|s| s.to_lowercase() == *i))
+    }
+
+    fn has_cookware(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.cookware.contains(&name)
+    }
+
+    /// How close the pantry is to the recipe
+    ///
+    /// Cookware is only checked if the pantry lists any.
+    pub fn suggestion(
+        &self,
+        ingredients: &[String],
+        cookware: &[String],
+        aliases: &Aliases,
+    ) -> Suggestion {
+        let missing = ingredients
+            .iter()
+            .filter(|i| !self.has_ingredient(i, aliases))
+            .cloned()
+            .collect::<Vec<_>>();
+        let missing_cookware = if self.cookware.is_empty() {
+            Vec::new()
+        } else {
+            cookware
+                .iter()
+                .filter(|c| !self.has_cookware(c))
+                .cloned()
+                .collect()
+        };
+        Suggestion {
+            have: ingredients.len() - missing.len(),
+            total: ingredients.len(),
+            missing,
+            missing_cookware,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub have: usize,
+    pub total: usize,
+    pub missing: Vec<String>,
+    pub missing_cookware: Vec<String>,
+}
+
+impl Suggestion {
+    /// Ready to cook first, then by missing items and by most ingredients
+    pub fn sort_key(&self) -> impl Ord {
+        (
+            self.missing.len() + self.missing_cookware.len(),
+            std::cmp::Reverse(self.total),
+        )
+    }
+}
+
+pub fn run(ctx: &Context, args: SuggestArgs) -> Result<()> {
+    let Some(mut pantry) = Pantry::load(&ctx.config, &ctx.base_path)? else {
+        bail!("No pantry file found. Add one to '.cooklang/pantry.conf'");
+    };
+    if args.ignore_cookware {
+        pantry.cookware.clear();
+    }
+    let aliases = ctx.aliases()?;
+    let parser = ctx.parser()?;
+
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let parsed = par_map(&entries, |entry| {
+        entry
+            .read()
+            .ok()
+            .and_then(|c| c.parse(parser).into_output())
+    });
+
+    let mut suggestions = Vec::new();
+    for (entry, recipe) in entries.iter().zip(parsed) {
+        let Some(recipe) = recipe else {
+            tracing::warn!("Skipping '{}', it has errors", entry.path());
+            continue;
+        };
+        let ingredients = required_ingredients(&recipe);
+        if ingredients.is_empty() {
+            continue;
+        }
+        let s = pantry.suggestion(&ingredients, &required_cookware(&recipe), aliases);
+        if s.missing.len() + s.missing_cookware.len() <= args.max_missing {
+            let name = entry
+                .path()
+                .strip_prefix(&ctx.base_path)
+                .unwrap_or(entry.path())
+                .with_extension("");
+            suggestions.push((name, s));
+        }
+    }
+
+    if suggestions.is_empty() {
+        eprintln!("Nothing to cook with what you have");
+        return Ok(());
+    }
+    suggestions.sort_by_cached_key(|(name, s)| (s.sort_key(), name.clone()));
+
+    let mut table = tabular::Table::new("{:<}  {:>}  {:<}");
+    for (name, s) in suggestions {
+        let have = format!("{}/{}", s.have, s.total);
+        let have = if s.missing.is_empty() && s.missing_cookware.is_empty() {
+            have.green().to_string()
+        } else {
+            have.yellow().to_string()
+        };
+        let missing = s
+            .missing
+            .iter()
+            .map(|i| i.to_string())
+            .chain(s.missing_cookware.iter().map(|c| c.italic().to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        table.add_row(
+            tabular::Row::new()
+                .with_cell(name)
+                .with_ansi_cell(have)
+                .with_ansi_cell(missing.dimmed().to_string()),
+        );
+    }
+    print!("{table}");
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anstream::{eprintln, print, println};
+use anyhow::{bail, Context as _, Result};
+use camino::Utf8PathBuf;
+use clap::{Args, Subcommand};
+use cooklang_fs::all_recipes_with;
+use cooklang_to_cooklang::LosslessRecipe;
+use owo_colors::OwoColorize;
+
+use crate::{
+    util::{is_valid_tag, par_map, write_atomic},
+    Context,
+};
+
+/// Metadata keys with tags
+const TAG_KEYS: &[&str] = &["tags", "tag"];
+
+/// Old and new value of each changed tags line
+type TagChanges = Vec<(String, String)>;
+
+#[derive(Debug, Args)]
+pub struct TagsArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List all tags with the number of recipes
+    #[command(visible_alias = "ls")]
+    List {
+        /// Sort by name instead of by count
+        #[arg(long)]
+        by_name: bool,
+    },
+    /// Rename a tag in all recipes
+    Rename {
+        old: String,
+        new: String,
+        /// Show the changes without writing them
+        #[arg(long, short = 'n')]
+        dry_run: bool,
+    },
+    /// Replace some tags with another one in all recipes
+    Merge {
+        #[arg(required = true)]
+        tags: Vec<String>,
+        /// Resulting tag
+        #[arg(long)]
+        into: String,
+        /// Show the changes without writing them
+        #[arg(long, short = 'n')]
+        dry_run: bool,
+    },
+    /// List the tags in the config that no recipe uses
+    Unused,
+}
+
+pub fn run(ctx: &Context, args: TagsArgs) -> Result<()> {
+    if !ctx.is_collection {
+        bail!("`tags` needs to run inside a collection");
+    }
+
+    match args.command {
+        Command::List { by_name } => {
+            let mut counts = count_tags(ctx)?.into_iter().collect::<Vec<_>>();
+            if !by_name {
+                counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+            }
+            let mut table = tabular::Table::new("{:>}  {:<}");
+            for (tag, count) in counts {
+                let tag = if is_valid_tag(&tag) {
+                    tag
+                } else {
+                    tag.yellow().to_string()
+                };
+                table.add_row(tabular::Row::new().with_cell(count).with_ansi_cell(tag));
+            }
+            print!("{table}");
+        }
+        Command::Rename { old, new, dry_run } => replace(ctx, &[old], &new, dry_run)?,
+        Command::Merge {
+            tags,
+            into,
+            dry_run,
+        } => replace(ctx, &tags, &into, dry_run)?,
+        Command::Unused => {
+            let used = count_tags(ctx)?;
+            let mut unused = ctx
+                .config
+                .ui
+                .tags
+                .keys()
+                .filter(|t| !used.contains_key(*t))
+                .collect::<Vec<_>>();
+            unused.sort();
+            for tag in unused {
+                println!("{tag}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tags and the number of recipes with them
+fn count_tags(ctx: &Context) -> Result<BTreeMap<String, usize>> {
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let extensions = ctx.config.extensions;
+    let tags = par_map(&entries, |entry| -> Result<HashSet<String>> {
+        let text = entry.read()?.into_text();
+        let recipe = match LosslessRecipe::parse(&text, extensions) {
+            Ok(r) => r,
+            Err(err) => {
+                tracing::warn!("Skipping '{}': {err}", entry.path());
+                return Ok(HashSet::new());
+            }
+        };
+        let mut seen = HashSet::new();
+        for key in TAG_KEYS {
+            for tag in recipe.get_metadata(key).map(split_tags).unwrap_or_default() {
+                seen.insert(tag.to_string());
+            }
+        }
+        Ok(seen)
+    });
+
+    let mut counts = BTreeMap::new();
+    for tag in tags
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+    {
+        *counts.entry(tag).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+fn split_tags(value: &str) -> Vec<&str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Replaces the tags in `from` with `to` in every recipe
+///
+/// Only the tags metadata lines change, the rest of the files is kept as is.
+fn replace(ctx: &Context, from: &[String], to: &str, dry_run: bool) -> Result<()> {
+    if !is_valid_tag(to) {
+        bail!("Invalid tag '{to}'. Tags are lower case words and numbers separated by '-'");
+    }
+
+    let entries =
+        all_recipes_with(&ctx.base_path, ctx.config.max_depth, &ctx.symlinks)?.collect::<Vec<_>>();
+    let extensions = ctx.config.extensions;
+    let edits = par_map(&entries, |entry| -> Result<Option<(TagChanges, String)>> {
+        let text = entry.read()?.into_text();
+        let mut recipe = match LosslessRecipe::parse(&text, extensions) {
+            Ok(r) => r,
+            Err(err) => {
+                tracing::warn!("Skipping '{}': {err}", entry.path());
+                return Ok(None);
+            }
+        };
+
+        let mut changes = Vec::new();
+
+        for key in TAG_KEYS {
+            let Some(value) = recipe.get_metadata(key) else {
+                continue;
+            };
+            let tags = split_tags(value);
+            if !tags.iter().any(|t| from.iter().any(|f| f == t)) {
+                continue;
+            }
+            let mut new_tags: Vec<&str> = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let tag = if from.iter().any(|f| f == tag) {
+                    to
+                } else {
+                    tag
+                };
+                if !new_tags.contains(&tag) {
+                    new_tags.push(tag);
+                }
+            }
+            let new_value = new_tags.join(", ");
+            changes.push((value.to_string(), new_value.clone()));
+            recipe.set_metadata(key, new_value);
+        }
+
+        Ok(recipe.is_edited().then(|| (changes, recipe.to_string())))
+    });
+
+    let mut updates: Vec<(Utf8PathBuf, String)> = Vec::new();
+    for (entry, edit) in entries.iter().zip(edits) {
+        let Some((changes, text)) = edit? else {
+            continue;
+        };
+        let rel = entry
+            .path()
+            .strip_prefix(&ctx.base_path)
+            .unwrap_or(entry.path());
+        for (old, new) in changes {
+            println!("{rel}: {} -> {}", old.red(), new.green());
+        }
+        updates.push((entry.path().to_path_buf(), text));
+    }
+
+    if updates.is_empty() {
+        eprintln!("No recipe has the tag");
+        return Ok(());
+    }
+    if dry_run {
+        eprintln!("{}", "Dry run, nothing was changed".yellow());
+        return Ok(());
+    }
+    for (path, text) in &updates {
+        write_atomic(path, text.as_bytes()).with_context(|| format!("Failed to write '{path}'"))?;
+    }
+    eprintln!("Updated {} recipe(s)", updates.len());
+
+    for tag in from {
+        if tag != to && ctx.config.ui.tags.contains_key(tag) {
+            tracing::warn!("'{tag}' has UI settings in the config, move them to '{to}'");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anstream::{eprintln, println};
+use anyhow::{bail, Context as _, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Subcommand};
+use owo_colors::OwoColorize;
+
+use crate::{cmd::mv::relative_to_base, Context, COOK_DIR};
+
+const TRASH_DIR: &str = "trash";
+
+#[derive(Debug, Args)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the removed recipes
+    #[command(visible_alias = "ls")]
+    List,
+    /// Restore a removed recipe and its images
+    Restore {
+        /// Id or name of the removed recipe, the last one if not given
+        recipe: Option<String>,
+    },
+    /// Permanently delete everything in the trash
+    Empty {
+        /// Don't ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+/// A group of files removed together
+struct TrashEntry {
+    id: String,
+    dir: Utf8PathBuf,
+    /// Paths relative to the collection
+    files: Vec<Utf8PathBuf>,
+}
+
+impl TrashEntry {
+    fn recipe(&self) -> Option<&Utf8Path> {
+        self.files
+            .iter()
+            .find(|f| f.extension() == Some("cook"))
+            .map(|f| f.as_path())
+    }
+
+    fn removed_at(&self) -> Option<SystemTime> {
+        let secs = self.id.split('-').next()?.parse().ok()?;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
+
+pub fn run(ctx: &Context, args: TrashArgs) -> Result<()> {
+    match args.command {
+        Command::List => {
+            let entries = entries(ctx)?;
+            if entries.is_empty() {
+                eprintln!("The trash is empty");
+            }
+            for entry in entries.iter().rev() {
+                let recipe = entry.recipe().map(|r| r.as_str()).unwrap_or("-");
+                let ago = entry.removed_at().map(ago).unwrap_or_default();
+                println!("{}  {recipe}  {}", entry.id.yellow(), ago.dimmed());
+            }
+        }
+        Command::Restore { recipe } => restore(ctx, recipe.as_deref())?,
+        Command::Empty { yes } => {
+            let dir = trash_dir(ctx);
+            if !dir.is_dir() {
+                eprintln!("The trash is empty");
+                return Ok(());
+            }
+            if !yes {
+                let confirm = inquire::Confirm::new("Permanently delete all removed recipes?")
+                    .with_default(false)
+                    .prompt()?;
+                if !confirm {
+                    return Ok(());
+                }
+            }
+            fs::remove_dir_all(&dir).context("Failed to empty the trash")?;
+            eprintln!("Trash emptied");
+        }
+    }
+    Ok(())
+}
+
+/// Moves the files to a new entry in the trash and returns its id
+///
+/// Files keep their path relative to the collection so they can be restored.
+pub fn put(ctx: &Context, base: &Utf8Path, files: &[Utf8PathBuf]) -> Result<String> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let trash = trash_dir(ctx);
+    let mut id = secs.to_string();
+    let mut n = 1;
+    while trash.join(&id).exists() {
+        n += 1;
+        id = format!("{secs}-{n}");
+    }
+    let dir = trash.join(&id);
+
+    let moves = files
+        .iter()
+        .map(|file| Ok((file, dir.join(relative_to_base(file, base)?))))
+        .collect::<Result<Vec<_>>>()?;
+    for (i, (file, dest)) in moves.iter().enumerate() {
+        let res = match dest.parent() {
+            Some(parent) => fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|()| fs::rename(file, dest));
+        if let Err(err) = res {
+            // put back the files already moved, so the recipe is not split
+            let mut restored = true;
+            for (file, dest) in moves[..i].iter().rev() {
+                if let Err(err) = fs::rename(dest, file) {
+                    tracing::error!("Failed to move '{dest}' back to '{file}': {err}");
+                    restored = false;
+                }
+            }
+            if restored {
+                let _ = fs::remove_dir_all(&dir);
+            }
+            return Err(err)
+                .with_context(|| format!("Failed to remove '{file}', nothing was changed"));
+        }
+    }
+    Ok(id)
+}
+
+fn restore(ctx: &Context, query: Option<&str>) -> Result<()> {
+    let entries = entries(ctx)?;
+    let entry = match query {
+        None => entries.last(),
+        Some(q) => entries.iter().rev().find(|e| {
+            e.id == q
+                || e.recipe().is_some_and(|r| {
+                    let q = q.trim_end_matches(".cook").to_lowercase();
+                    let r = r.with_extension("").as_str().to_lowercase();
+                    r == q || r.ends_with(&format!("/{q}"))
+                })
+        }),
+    };
+    let Some(entry) = entry else {
+        match query {
+            Some(q) => bail!("Nothing in the trash matches '{q}'"),
+            None => bail!("The trash is empty"),
+        }
+    };
+
+    for file in &entry.files {
+        let dest = ctx.base_path.join(file);
+        if dest.exists() {
+            bail!("Can't restore, file already exists: '{dest}'");
+        }
+    }
+    for file in &entry.files {
+        let dest = ctx.base_path.join(file);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(entry.dir.join(file), &dest)
+            .with_context(|| format!("Failed to restore '{file}'"))?;
+        println!("{} {file}", "Restored".green().bold());
+    }
+    fs::remove_dir_all(&entry.dir)?;
+    Ok(())
+}
+
+fn trash_dir(ctx: &Context) -> Utf8PathBuf {
+    ctx.base_path.join(COOK_DIR).join(TRASH_DIR)
+}
+
+/// All entries in the trash, oldest first
+fn entries(ctx: &Context) -> Result<Vec<TrashEntry>> {
+    let trash = trash_dir(ctx);
+    if !trash.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for e in trash.read_dir_utf8()? {
+        let e = e?;
+        if !e.file_type()?.is_dir() {
+            continue;
+        }
+        let mut files = Vec::new();
+        collect_files(e.path(), e.path(), &mut files)?;
+        entries.push(TrashEntry {
+            id: e.file_name().to_string(),
+            dir: e.path().to_path_buf(),
+            files,
+        });
+    }
+    entries.sort_by_key(|e| (e.removed_at(), e.id.clone()));
+    Ok(entries)
+}
+
+fn collect_files(root: &Utf8Path, dir: &Utf8Path, files: &mut Vec<Utf8PathBuf>) -> Result<()> {
+    for e in dir.read_dir_utf8()? {
+        let e = e?;
+        if e.file_type()?.is_dir() {
+            collect_files(root, e.path(), files)?;
+        } else {
+            let rel = e
+                .path()
+                .strip_prefix(root)
+                .expect("file outside trash entry");
+            files.push(rel.to_path_buf());
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+fn ago(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
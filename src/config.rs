@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     fs::{self, File},
     io::{self, Read},
@@ -15,6 +15,8 @@ use crate::{APP_NAME, COOK_DIR, UTF8_PATH_PANIC};
 
 pub const CONFIG_FILE: &str = "config.toml";
 pub const AUTO_AISLE: &str = "aisle.conf";
+pub const AUTO_ALIASES: &str = "aliases.txt";
+pub const AUTO_PANTRY: &str = "pantry.conf";
 pub const AUTO_UNITS: &str = "units.toml";
 pub const DEFAULT_CONFIG_FILE: &str = "default-config.toml";
 pub const CHEF_CONFIG_FILE: &str = "chef-config.toml";
@@ -23,6 +25,8 @@ pub const CHEF_CONFIG_FILE: &str = "chef-config.toml";
 pub struct ChefConfig {
     pub default_collection: Option<Utf8PathBuf>,
     pub editor_command: Option<Vec<String>>,
+    /// Author of new recipes
+    pub author: Option<String>,
 }
 
 impl ChefConfig {
@@ -59,6 +63,7 @@ impl Default for ChefConfig {
         Self {
             default_collection: None,
             editor_command: None,
+            author: None,
         }
     }
 }
@@ -82,6 +87,16 @@ pub struct Config {
     pub ui: UiConfig,
     #[serde(skip_serializing_if = "is_default")]
     pub export: ExportConfig,
+    /// Tags added to new recipes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub default_tags: Vec<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub symlinks: SymlinksConfig,
+    #[serde(skip_serializing_if = "is_default")]
+    pub serve: ServeConfig,
+    /// Access control for `chef serve`
+    #[serde(skip_serializing_if = "AuthConfig::is_empty")]
+    pub auth: AuthConfig,
 }
 
 impl Default for Config {
@@ -95,6 +110,10 @@ impl Default for Config {
             load: Default::default(),
             ui: Default::default(),
             export: Default::default(),
+            default_tags: Vec::new(),
+            symlinks: Default::default(),
+            serve: Default::default(),
+            auth: Default::default(),
         }
     }
 }
@@ -106,14 +125,88 @@ pub struct Load {
     pub units: Vec<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aisle: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pantry: Option<PathBuf>,
 }
 
 impl Load {
     fn is_empty(&self) -> bool {
-        self.units.is_empty() && self.aisle.is_none()
+        self.units.is_empty()
+            && self.aisle.is_none()
+            && self.aliases.is_none()
+            && self.pantry.is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SymlinksConfig {
+    /// Follow links to recipes and folders
+    pub follow: bool,
+    /// Folders outside the collection that links can point to
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ServeConfig {
+    /// PEM certificate chain, serves HTTPS with `tls_key`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
+    /// Port that redirects plain HTTP to HTTPS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_port: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// HTTP Basic users by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub users: BTreeMap<String, AuthUser>,
+    /// Bearer tokens, also used to log in to the web UI
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<AuthToken>,
+}
+
+impl AuthConfig {
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.tokens.is_empty()
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AuthUser {
+    /// Argon2 hash from `chef serve --hash-password`
+    pub password: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AuthToken {
+    pub token: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// What a user can do in the web UI
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Browse the recipes and see the shopping list
+    #[default]
+    Read,
+    /// Also create and edit recipes and change the shopping list
+    Write,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct UiConfig {
     pub tags: HashMap<String, TagProps>,
@@ -195,24 +288,41 @@ impl Config {
     }
 
     pub fn aisle(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
-        self.load
-            .aisle
-            .as_ref()
-            .map(|a| resolve_path(base_path, a))
-            .or_else(|| {
-                let auto = base_path.join(COOK_DIR).join(AUTO_AISLE);
-                tracing::trace!("checking auto aisle file: {auto}");
-                auto.is_file().then_some(auto)
-            })
-            .or_else(|| {
-                let global = global_file_path(AUTO_AISLE).ok()?;
-                tracing::trace!("checking global auto aisle file: {global}");
-                global.is_file().then_some(global)
+        extra_file(base_path, self.load.aisle.as_deref(), AUTO_AISLE)
+    }
+
+    pub fn aliases(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        extra_file(base_path, self.load.aliases.as_deref(), AUTO_ALIASES)
+    }
+
+    pub fn pantry(&self, base_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        extra_file(base_path, self.load.pantry.as_deref(), AUTO_PANTRY)
+    }
+
+    pub fn symlinks(&self, base_path: &Utf8Path) -> cooklang_fs::Symlinks {
+        let allowed_targets = self
+            .symlinks
+            .allow
+            .iter()
+            .filter_map(|p| {
+                let path = resolve_path(base_path, p);
+                match path.canonicalize_utf8() {
+                    Ok(target) => Some(target),
+                    Err(err) => {
+                        tracing::warn!("Ignoring allowed symlink target '{path}': {err}");
+                        None
+                    }
+                }
             })
+            .collect();
+        cooklang_fs::Symlinks {
+            follow: self.symlinks.follow,
+            allowed_targets,
+        }
     }
 
     pub fn units(&self, base_path: &Utf8Path) -> Vec<Utf8PathBuf> {
-        (!self.load.is_empty())
+        (!self.load.units.is_empty())
             .then(|| {
                 self.load
                     .units
@@ -234,6 +344,21 @@ impl Config {
     }
 }
 
+/// The configured file or else `auto_name` in the collection or global config
+fn extra_file(base_path: &Utf8Path, path: Option<&Path>, auto_name: &str) -> Option<Utf8PathBuf> {
+    path.map(|p| resolve_path(base_path, p))
+        .or_else(|| {
+            let auto = base_path.join(COOK_DIR).join(auto_name);
+            tracing::trace!("checking auto file: {auto}");
+            auto.is_file().then_some(auto)
+        })
+        .or_else(|| {
+            let global = global_file_path(auto_name).ok()?;
+            tracing::trace!("checking global auto file: {global}");
+            global.is_file().then_some(global)
+        })
+}
+
 pub fn resolve_path(base_path: &Utf8Path, path: &Path) -> Utf8PathBuf {
     let path = Utf8Path::from_path(path).expect(UTF8_PATH_PANIC);
     if path.is_absolute() {
@@ -13,7 +13,9 @@ use util::metadata_validator;
 mod cmd;
 
 // other modules
+mod aliases;
 mod args;
+mod cache;
 mod config;
 mod util;
 
@@ -58,6 +60,14 @@ pub fn main() -> Result<()> {
         Command::GenerateCompletions(args) => cmd::generate_completions::run(args),
         Command::New(args) => cmd::new::run(args, &ctx),
         Command::Edit(args) => cmd::edit::run(args, &ctx),
+        Command::Fmt(args) => cmd::fmt::run(&ctx, args),
+        Command::Mv(args) => cmd::mv::run(&ctx, args),
+        Command::Rm(args) => cmd::rm::run(&ctx, args),
+        Command::Trash(args) => cmd::trash::run(&ctx, args),
+        Command::Stats(args) => cmd::stats::run(&ctx, args),
+        Command::Tags(args) => cmd::tags::run(&ctx, args),
+        Command::Ingredients(args) => cmd::ingredients::run(&ctx, args),
+        Command::Suggest(args) => cmd::suggest::run(&ctx, args),
     }
 }
 
@@ -74,6 +84,7 @@ fn init_color(color: colorchoice_clap::Color) -> ColorContext {
 
 pub struct Context {
     parser: OnceCell<CooklangParser>,
+    aliases: OnceCell<aliases::Aliases>,
     recipe_index: LazyFsIndex,
     global_args: GlobalArgs,
     base_path: Utf8PathBuf,
@@ -81,6 +92,7 @@ pub struct Context {
     chef_config: config::ChefConfig,
     color: ColorContext,
     is_collection: bool,
+    symlinks: cooklang_fs::Symlinks,
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -109,19 +121,23 @@ fn configure_context(args: GlobalArgs, color_ctx: ColorContext) -> Result<Contex
     };
     config.override_with_args(&args);
 
+    let symlinks = config.symlinks(base_path);
     let recipe_index = cooklang_fs::new_index(base_path, config.max_depth)?
         .config_dir(COOK_DIR.to_string())
+        .symlinks(symlinks.clone())
         .lazy();
 
     Ok(Context {
         is_collection: base_path.join(COOK_DIR).is_dir(),
         base_path: base_path.to_owned(),
         parser: OnceCell::new(),
+        aliases: OnceCell::new(),
         recipe_index,
         config,
         chef_config,
         global_args: args,
         color: color_ctx,
+        symlinks,
     })
 }
 
@@ -133,10 +149,21 @@ impl Context {
             .get_or_try_init(|| configure_parser(&self.config, &self.base_path))
     }
 
+    fn aliases(&self) -> Result<&aliases::Aliases> {
+        self.aliases
+            .get_or_try_init(|| match self.config.aliases(&self.base_path) {
+                Some(path) => {
+                    tracing::debug!("Loading aliases {path}");
+                    aliases::Aliases::load(&path)
+                }
+                None => Ok(aliases::Aliases::default()),
+            })
+    }
+
     fn checker(
         &self,
         relative_to: Option<&Utf8Path>,
-    ) -> Option<cooklang::analysis::RecipeRefCheck> {
+    ) -> Option<cooklang::analysis::RecipeRefCheck<'_>> {
         if self.config.recipe_ref_check {
             let relative_to = relative_to.map(|r| {
                 r.to_path_buf()
@@ -160,7 +187,7 @@ impl Context {
         }
     }
 
-    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions {
+    fn parse_options(&self, relative_to: Option<&Utf8Path>) -> ParseOptions<'_> {
         ParseOptions {
             recipe_ref_check: self.checker(relative_to),
             metadata_validator: Some(Box::new(metadata_validator)),
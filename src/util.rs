@@ -2,8 +2,10 @@ use std::borrow::Cow;
 
 use anyhow::{bail, Context as _, Result};
 
-use camino::Utf8Path;
-use cooklang::{analysis::CheckResult, Metadata};
+use camino::{Utf8Path, Utf8PathBuf};
+use cooklang::{
+    analysis::CheckResult, model::Ingredient, Metadata, Modifiers, ScalableRecipe, ScalableValue,
+};
 use cooklang_fs::{RecipeContent, RecipeEntry};
 
 use crate::Context;
@@ -83,7 +85,7 @@ impl Input {
         }
     }
 
-    pub fn text(&self) -> Result<Cow<str>> {
+    pub fn text(&self) -> Result<Cow<'_, str>> {
         Ok(match self {
             Input::File { entry, .. } => entry.read()?.into_text().into(),
             Input::Stdin { text, .. } => text.as_str().into(),
@@ -127,6 +129,49 @@ pub fn meta_name(meta: &cooklang::Metadata) -> Option<&str> {
         .map(|n| n.as_str())
 }
 
+/// The ingredient is a reference to another recipe
+pub fn is_recipe_ref(igr: &Ingredient<ScalableValue>) -> bool {
+    igr.modifiers().contains(Modifiers::RECIPE)
+        || igr.name.starts_with("./")
+        || igr.name.starts_with("../")
+}
+
+/// Ingredients needed to cook the recipe
+///
+/// Optional ingredients, references to other ingredients and to other recipes
+/// are skipped.
+pub fn required_ingredients(recipe: &ScalableRecipe) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for igr in &recipe.ingredients {
+        let m = igr.modifiers();
+        if !igr.relation.is_definition()
+            || !m.should_be_listed()
+            || m.is_optional()
+            || is_recipe_ref(igr)
+        {
+            continue;
+        }
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(&igr.name)) {
+            names.push(igr.name.clone());
+        }
+    }
+    names
+}
+
+/// Cookware needed to cook the recipe
+pub fn required_cookware(recipe: &ScalableRecipe) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for item in &recipe.cookware {
+        if !item.relation.is_definition() || item.modifiers().is_optional() {
+            continue;
+        }
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(&item.name)) {
+            names.push(item.name.clone());
+        }
+    }
+    names
+}
+
 pub struct CachedRecipeEntry {
     entry: RecipeEntry,
     metadata: once_cell::unsync::OnceCell<Box<Metadata>>,
@@ -235,6 +280,75 @@ const IS_VALID_TAG_MSG: &str =
 
 const TAG_TOO_LONG_MSG: &str = "The tag is too long";
 
+/// Maps the items in parallel and returns the results in the same order
+///
+/// Uses one thread per core, each one taking the next item when it finishes
+/// the previous, so slow items don't block the rest.
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results = Vec::with_capacity(items.len());
+    results.resize_with(items.len(), || None);
+    std::thread::scope(|s| {
+        let workers = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else { break };
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            let done = worker
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e));
+            for (i, r) in done {
+                results[i] = Some(r);
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.expect("item not mapped"))
+        .collect()
+}
+
+/// Writes to a temporary file first so readers never see half a file
+pub fn write_atomic(path: &Utf8Path, data: &[u8]) -> std::io::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = Utf8PathBuf::from(format!("{path}.{}-{n}.tmp", std::process::id()));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,4 +367,12 @@ mod tests {
         assert!(!is_valid_tag("other/characters"));
         assert!(!is_valid_tag("other@[]chara€cters"));
     }
+
+    #[test]
+    fn par_map_keeps_order() {
+        let items = (0..1000).collect::<Vec<u32>>();
+        let mapped = par_map(&items, |i| i * 2);
+        assert_eq!(mapped, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+        assert!(par_map(&[] as &[u32], |i| *i).is_empty());
+    }
 }
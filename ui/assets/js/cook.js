@@ -0,0 +1,90 @@
+/* Cook mode, one step at a time */
+
+function cookRoot() {
+  return document.getElementById("cook-mode");
+}
+
+function cookShow(root, index) {
+  const steps = root.querySelectorAll("[data-cook-step]");
+  const prev = root.querySelector("[data-cook-prev]");
+  const next = root.querySelector("[data-cook-next]");
+  if (steps.length === 0) {
+    prev.hidden = true;
+    next.hidden = true;
+    return;
+  }
+
+  index = Math.max(0, Math.min(index, steps.length - 1));
+  steps.forEach((el, i) => (el.hidden = i !== index));
+  root.setAttribute("data-cook-current", index);
+  root.querySelector("[data-cook-counter]").textContent =
+    `${index + 1} / ${steps.length}`;
+  prev.disabled = index === 0;
+  next.disabled = index === steps.length - 1;
+
+  // remembered in this device only
+  const key = "cook-step:" + root.getAttribute("data-cook-key");
+  localStorage.setItem(key, index);
+}
+
+function cookMove(delta) {
+  const root = cookRoot();
+  if (!root) return;
+  const current = root.getAttribute("data-cook-current") * 1 || 0;
+  cookShow(root, current + delta);
+}
+
+function cookInit(el) {
+  const root = el.id === "cook-mode" ? el : el.querySelector("#cook-mode");
+  if (!root) return;
+  const key = "cook-step:" + root.getAttribute("data-cook-key");
+  cookShow(root, localStorage.getItem(key) * 1 || 0);
+}
+
+// the script runs again when navigating back to the page
+if (!window.cookModeListeners) {
+  window.cookModeListeners = true;
+
+  document.addEventListener("click", (ev) => {
+    if (ev.target.closest("[data-cook-prev]")) cookMove(-1);
+    if (ev.target.closest("[data-cook-next]")) cookMove(1);
+  });
+
+  document.addEventListener("keydown", (ev) => {
+    if (ev.ctrlKey || ev.altKey || ev.metaKey) return;
+    if (ev.target.closest("input, select, textarea")) return;
+    if (["ArrowRight", "PageDown", " "].includes(ev.key)) {
+      if (!cookRoot()) return;
+      ev.preventDefault();
+      cookMove(1);
+    } else if (["ArrowLeft", "PageUp"].includes(ev.key)) {
+      if (!cookRoot()) return;
+      ev.preventDefault();
+      cookMove(-1);
+    }
+  });
+
+  let touchStart = null;
+  document.addEventListener(
+    "touchstart",
+    (ev) => {
+      if (ev.touches.length !== 1) return;
+      touchStart = { x: ev.touches[0].clientX, y: ev.touches[0].clientY };
+    },
+    { passive: true },
+  );
+  document.addEventListener("touchend", (ev) => {
+    if (!touchStart) return;
+    const dx = ev.changedTouches[0].clientX - touchStart.x;
+    const dy = ev.changedTouches[0].clientY - touchStart.y;
+    touchStart = null;
+    // mostly horizontal, to not get in the way of scrolling
+    if (Math.abs(dx) > 50 && Math.abs(dx) > 2 * Math.abs(dy)) {
+      cookMove(dx < 0 ? 1 : -1);
+    }
+  });
+
+  htmx.onLoad(cookInit);
+}
+
+cookInit(document.body);
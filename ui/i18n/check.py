@@ -60,6 +60,22 @@ safelist = set(
         "r.convertSelector.imperial",
         "openInEditor.error",
         "openInEditor.success",
+        "edit.saved",
+        "edit.conflict",
+        "edit.error",
+        "upload.saved",
+        "upload.noFile",
+        "upload.notImage",
+        "upload.error",
+        "list.added",
+        "list.error",
+        "list.hasErrors",
+        "list.notFound",
+        "search.sort.path",
+        "search.sort.name",
+        "search.sort.modified",
+        "search.sort.time",
+        "login.error",
     ]
 )
 